@@ -79,9 +79,8 @@ terminate(_Reason, State) ->
 code_change(_OldVsn, State, _Extra) ->
     {ok, State}.
 
-% Tag: [H|T], improper list -> tuple
-handle_call({port_command,C,I1,I2}, {F,[H|T]}, #state{port=P}=S) ->
-    true = port_command(P, term_to_binary({C,I1,I2,F,H,T})),
+handle_call({port_command,C,I1,I2}, From, #state{port=P}=S) ->
+    true = port_command(P, term_to_binary({call,From,{C,I1,I2}})),
     {noreply, S};
 handle_call({setup,Args}, _From, State) ->
     setup(Args, State);
@@ -93,8 +92,8 @@ handle_cast(_Request, State) ->
     {stop, enotsup, State}.
 
 handle_info({P,{data,B}}, #state{port=P}=S) ->
-    {R,V,F,H,T} = binary_to_term(B),
-    ok = gen_server:reply({F,[H|T]}, {R,V}),
+    {reply,From,Reply} = binary_to_term(B),
+    ok = gen_server:reply(From, Reply),
     {noreply, S};
 handle_info({'EXIT',P,Reason}, #state{port=P}=S) ->
     {stop, {port_close,Reason}, S#state{port = undefined}};
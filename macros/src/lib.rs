@@ -0,0 +1,151 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{parse_macro_input, FnArg, ItemFn, Lit, LitStr, Path, ReturnType, Type};
+
+// #[port_export], #[port_export(name = "plus", code = 'a')]: the function stays as it is,
+//  a module of the same name holds its EXPORT for ei::port_exports!. The generated code
+//  names the crate ::ei, crate = "path" when it is known by another name. Arguments are
+//  decoded into owned values, so they can not be references
+#[proc_macro_attribute]
+pub fn port_export(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = Args::default();
+    let parser = syn::meta::parser(|meta| args.parse(meta));
+    parse_macro_input!(attr with parser);
+    let item = parse_macro_input!(item as ItemFn);
+    match export(args, item) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    name: Option<String>,
+    code: Option<i64>,
+    krate: Option<Path>,
+}
+
+impl Args {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("name") {
+            self.name = Some(meta.value()?.parse::<LitStr>()?.value());
+        } else if meta.path.is_ident("code") {
+            self.code = match meta.value()?.parse::<Lit>()? {
+                Lit::Int(i) => Some(i.base10_parse::<i64>()?),
+                Lit::Char(c) => Some(c.value() as i64),
+                lit => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "expected an integer or a char",
+                    ))
+                }
+            };
+        } else if meta.path.is_ident("crate") {
+            self.krate = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+        } else {
+            return Err(meta.error("expected `name = \"..\"`, `code = ..` or `crate = \"..\"`"));
+        }
+        Ok(())
+    }
+}
+
+fn export(args: Args, item: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &item.sig;
+    let ident = &sig.ident;
+
+    let name = args.name.unwrap_or_else(|| ident.to_string());
+    let code = match args.code {
+        Some(i) => quote!(::std::option::Option::Some(#i)),
+        None => quote!(::std::option::Option::None),
+    };
+    let ei = match args.krate {
+        Some(path) => quote!(#path),
+        None => quote!(::ei),
+    };
+
+    if let Some(t) = &sig.asyncness {
+        return Err(syn::Error::new_spanned(
+            t,
+            "async functions can not be exported",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "generic functions can not be exported",
+        ));
+    }
+
+    let mut vars = Vec::new();
+    let mut types = Vec::new();
+    for (i, input) in sig.inputs.iter().enumerate() {
+        match input {
+            FnArg::Typed(pt) => {
+                if let Type::Reference(_) = &*pt.ty {
+                    return Err(syn::Error::new_spanned(
+                        &pt.ty,
+                        "references can not be exported, take an owned type such as String or Vec<u8>",
+                    ));
+                }
+                if let Some(t) = find_self(quote!(#pt)) {
+                    return Err(syn::Error::new_spanned(
+                        t,
+                        "argument types can not name Self",
+                    ));
+                }
+                vars.push(format_ident!("__arg{}", i));
+                types.push(&pt.ty);
+            }
+            FnArg::Receiver(r) => {
+                return Err(syn::Error::new_spanned(r, "methods can not be exported"))
+            }
+        }
+    }
+    let arity = vars.len();
+
+    let call = quote!(#ident(#(#vars),*));
+    let reply = match &sig.output {
+        ReturnType::Default => quote!({ #call; #ei::port::Export::done() }),
+        ReturnType::Type(_, ty) if is_result(ty) => quote!(#ei::port::Export::result(#call)),
+        ReturnType::Type(_, _) => quote!(#ei::port::Export::ok(&#call)),
+    };
+
+    let vis = &item.vis;
+    // beside the function, where its argument types mean what they do to it
+    let call_ident = format_ident!("__ei_call_{}", ident, span = Span::call_site());
+    Ok(quote! {
+        #item
+
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        fn #call_ident(
+            de: &mut #ei::Deserializer<&[u8]>,
+        ) -> ::std::result::Result<::std::vec::Vec<u8>, #ei::Error> {
+            #(let #vars: #types = #ei::port::Export::arg(de)?;)*
+            #reply
+        }
+
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        #vis mod #ident {
+            pub const EXPORT: #ei::port::Export = #ei::port::Export::new(#name, #code, #arity, super::#call_ident);
+        }
+    })
+}
+
+fn find_self(ts: TokenStream2) -> Option<proc_macro2::Ident> {
+    ts.into_iter().find_map(|t| match t {
+        TokenTree::Ident(i) if i == "Self" => Some(i),
+        TokenTree::Group(g) => find_self(g.stream()),
+        _ => None,
+    })
+}
+
+fn is_result(ty: &Type) -> bool {
+    match ty {
+        Type::Path(tp) => tp.path.segments.last().is_some_and(|s| s.ident == "Result"),
+        _ => false,
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use serde::{de, ser};
+
+use crate::binary::Binary;
+use crate::error::Error;
+use crate::io::Writer;
+use crate::raw::RAW_TERM;
+use crate::ser::Serializer;
+use crate::term::Atom;
+
+struct Entry {
+    name: String,
+    bytes: Vec<u8>,
+}
+
+#[derive(Clone)]
+enum Repr {
+    Static(&'static str, &'static [u8]),
+    Interned(Arc<Entry>),
+}
+
+// a shared, cheap to clone atom that is encoded once and written from those bytes; to
+//  any other serializer it is its name. Decoding one takes it from the process-wide table
+//  without adding to it or allocating, unless the atom is not there; an AtomTable, used
+//  as a DeserializeSeed, interns what it decodes
+#[derive(Clone)]
+pub struct InternedAtom(Repr);
+
+impl InternedAtom {
+    pub const OK: InternedAtom = InternedAtom(Repr::Static("ok", b"\x77\x02ok"));
+    pub const ERROR: InternedAtom = InternedAtom(Repr::Static("error", b"\x77\x05error"));
+    pub const TRUE: InternedAtom = InternedAtom(Repr::Static("true", b"\x77\x04true"));
+    pub const FALSE: InternedAtom = InternedAtom(Repr::Static("false", b"\x77\x05false"));
+    pub const UNDEFINED: InternedAtom =
+        InternedAtom(Repr::Static("undefined", b"\x77\x09undefined"));
+
+    const STATIC: [InternedAtom; 5] = [
+        InternedAtom::OK,
+        InternedAtom::ERROR,
+        InternedAtom::TRUE,
+        InternedAtom::FALSE,
+        InternedAtom::UNDEFINED,
+    ];
+
+    // interned in the process-wide table
+    pub fn new(name: &str) -> Result<Self, Error> {
+        if let Some(a) = InternedAtom::get(name) {
+            return Ok(a);
+        }
+        table()
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .intern(name)
+    }
+
+    // from the process-wide table, under its read lock
+    fn get(name: &str) -> Option<Self> {
+        table().read().unwrap_or_else(|e| e.into_inner()).get(name)
+    }
+
+    // encoded, but in no table
+    fn uninterned(name: &str) -> Result<Self, Error> {
+        let mut writer = Writer::new(Vec::new());
+        writer.write_atom_utf8(name)?;
+        Ok(InternedAtom(Repr::Interned(Arc::new(Entry {
+            name: name.to_owned(),
+            bytes: writer.into_inner(),
+        }))))
+    }
+
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Static(s, _) => s,
+            Repr::Interned(e) => &e.name,
+        }
+    }
+
+    // the encoded term
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.0 {
+            Repr::Static(_, v) => v,
+            Repr::Interned(e) => &e.bytes,
+        }
+    }
+}
+
+impl PartialEq for InternedAtom {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for InternedAtom {}
+
+impl Hash for InternedAtom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for InternedAtom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("InternedAtom").field(&self.as_str()).finish()
+    }
+}
+
+impl From<&InternedAtom> for Atom {
+    fn from(a: &InternedAtom) -> Self {
+        Atom::from(a.as_str())
+    }
+}
+
+// a per-connection alternative to the process-wide table
+#[derive(Default)]
+pub struct AtomTable {
+    map: HashMap<String, InternedAtom>,
+}
+
+impl AtomTable {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> Result<InternedAtom, Error> {
+        if let Some(a) = self.get(name) {
+            return Ok(a);
+        }
+        let a = InternedAtom::uninterned(name)?;
+        self.map.insert(name.to_owned(), a.clone());
+        Ok(a)
+    }
+
+    // a static atom or one already interned, never adding to the table
+    pub fn get(&self, name: &str) -> Option<InternedAtom> {
+        InternedAtom::STATIC
+            .iter()
+            .find(|a| a.as_str() == name)
+            .or_else(|| self.map.get(name))
+            .cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+fn table() -> &'static RwLock<AtomTable> {
+    static TABLE: OnceLock<RwLock<AtomTable>> = OnceLock::new();
+    TABLE.get_or_init(Default::default)
+}
+
+impl ser::Serialize for InternedAtom {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        if is_ei::<S>() {
+            serializer.serialize_newtype_struct(RAW_TERM, &Binary(self.as_bytes()))
+        } else {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+}
+
+// whether S is this crate's Serializer, the only one the encoded bytes mean anything to
+fn is_ei<S>() -> bool {
+    let name = |s: &'static str| s.split('<').next();
+    name(std::any::type_name::<S>()) == name(std::any::type_name::<&mut Serializer<()>>())
+}
+
+// the name is looked up as decoded, and copied only when it is not found
+struct AtomVisitor<'a>(Option<&'a mut AtomTable>);
+
+impl<'de> de::Visitor<'de> for AtomVisitor<'_> {
+    type Value = InternedAtom;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an atom")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match self.0 {
+            Some(table) => table.intern(v),
+            None => InternedAtom::get(v).map_or_else(|| InternedAtom::uninterned(v), Ok),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for InternedAtom {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(AtomVisitor(None))
+    }
+}
+
+impl<'de> de::DeserializeSeed<'de> for &mut AtomTable {
+    type Value = InternedAtom;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(AtomVisitor(Some(self)))
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use serde::de::DeserializeSeed;
+
+    use crate::term;
+    use crate::{from_reader, to_vec, Deserializer};
+
+    use super::{AtomTable, InternedAtom};
+
+    #[test]
+    fn statics() {
+        for a in InternedAtom::STATIC.iter() {
+            assert_eq!(to_vec(&term::Atom::from(a.as_str())).unwrap(), a.as_bytes(), "{:?}", a);
+            assert_eq!(a.as_bytes(), to_vec(a).unwrap().as_slice(), "{:?}", a);
+        }
+        assert!(matches!(InternedAtom::new("ok").unwrap().0, super::Repr::Static(..)));
+    }
+
+    #[test]
+    fn is_ei() {
+        assert!(super::is_ei::<&mut crate::Serializer<Vec<u8>>>());
+        assert!(super::is_ei::<&mut crate::Serializer<&mut Vec<u8>>>());
+        assert!(!super::is_ei::<crate::Serializer<Vec<u8>>>());
+        assert!(!super::is_ei::<&mut Vec<u8>>());
+    }
+
+    #[test]
+    fn intern() {
+        let a = InternedAtom::new("interned").unwrap();
+        let b = InternedAtom::new("interned").unwrap();
+        match (&a.0, &b.0) {
+            (super::Repr::Interned(x), super::Repr::Interned(y)) => assert!(std::sync::Arc::ptr_eq(x, y)),
+            _ => panic!("{:?}", a),
+        }
+        assert_eq!(vec![0x77, 0x08], to_vec(&a).unwrap()[..2].to_vec());
+        assert!(InternedAtom::new(&"a".repeat(256)).is_err());
+
+        let mut table = AtomTable::new();
+        assert_eq!(a, table.intern("interned").unwrap());
+        table.intern("ok").unwrap();
+        assert_eq!(1, table.len());
+    }
+
+    #[test]
+    fn deserialize() {
+        for input in vec![
+            vec![0x64, 0x00, 0x02, 0x6f, 0x6b],
+            vec![0x73, 0x02, 0x6f, 0x6b],
+            vec![0x77, 0x02, 0x6f, 0x6b],
+            vec![0x76, 0x00, 0x02, 0x6f, 0x6b],
+        ] {
+            let actual: InternedAtom = from_reader(input.as_slice()).unwrap();
+            assert_eq!(InternedAtom::OK, actual, "{:?}", input);
+        }
+        assert!(from_reader::<_, InternedAtom>(&[0x61, 0x01][..]).is_err());
+
+        let a = InternedAtom::new("decoded").unwrap();
+        let actual: InternedAtom = from_reader(&[0x77, 0x07, 0x64, 0x65, 0x63, 0x6f, 0x64, 0x65, 0x64][..]).unwrap();
+        match (&a.0, &actual.0) {
+            (super::Repr::Interned(x), super::Repr::Interned(y)) => assert!(std::sync::Arc::ptr_eq(x, y)),
+            _ => panic!("{:?}", actual),
+        }
+        let actual: InternedAtom = from_reader(&[0x77, 0x05, 0x66, 0x72, 0x65, 0x73, 0x68][..]).unwrap();
+        assert_eq!(vec![0x77, 0x05, 0x66, 0x72, 0x65, 0x73, 0x68], actual.as_bytes());
+        assert!(super::table().read().unwrap().get("fresh").is_none());
+        let actual: InternedAtom = from_reader(&[0x64, 0x00, 0x04, 0x63, 0x61, 0x66, 0xe9][..]).unwrap();
+        assert_eq!("café", actual.as_str());
+
+        let mut table = AtomTable::new();
+        let input = vec![0x77, 0x03, 0x61, 0x62, 0x63];
+        let actual = (&mut table).deserialize(&mut Deserializer::new(input.as_slice())).unwrap();
+        assert_eq!("abc", actual.as_str());
+        assert_eq!(1, table.len());
+    }
+}
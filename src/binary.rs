@@ -0,0 +1,279 @@
+use std::fmt;
+
+use serde::{de, ser};
+
+pub const CHARLIST: &str = "$ei::Charlist";
+pub const IODATA: &str = "$ei::IoData";
+pub const IOLIST: &str = "$ei::IoList";
+
+// <<...>>, BINARY_EXT
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Binary<B = Vec<u8>>(pub B);
+
+// "...", a list of code points: STRING_EXT when every char is latin-1, LIST_EXT otherwise
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Charlist<S = String>(pub S);
+
+// encoded as a binary, decoded from any iodata, flattened as iolist_to_binary/1 does
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct IoData<B = Vec<u8>>(pub B);
+
+// encoded as a list of binaries without copying, decoded from any iodata into
+//  one chunk per binary or run of bytes
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct IoList<B = Vec<u8>>(pub Vec<B>);
+
+impl<B> ser::Serialize for Binary<B>
+where
+    B: AsRef<[u8]>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_bytes(self.0.as_ref())
+    }
+}
+
+impl<T> ser::Serialize for Charlist<T>
+where
+    T: AsRef<str>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(CHARLIST, self.0.as_ref())
+    }
+}
+
+impl<B> ser::Serialize for IoData<B>
+where
+    B: AsRef<[u8]>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_bytes(self.0.as_ref())
+    }
+}
+
+impl<B> ser::Serialize for IoList<B>
+where
+    B: AsRef<[u8]>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.collect_seq(self.0.iter().map(|b| Binary(b.as_ref())))
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> de::Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a binary")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v)
+    }
+}
+
+struct StringVisitor;
+
+impl<'de> de::Visitor<'de> for StringVisitor {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a charlist")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.to_owned())
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v)
+    }
+}
+
+struct ChunksVisitor;
+
+impl<'de> de::Visitor<'de> for ChunksVisitor {
+    type Value = Vec<Vec<u8>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("iodata")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(Binary(v)) = seq.next_element()? {
+            vec.push(v);
+        }
+        Ok(vec)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Binary {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_byte_buf(BytesVisitor).map(Binary)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Charlist {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_newtype_struct(CHARLIST, StringVisitor)
+            .map(Charlist)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for IoData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_newtype_struct(IODATA, BytesVisitor)
+            .map(IoData)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for IoList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_newtype_struct(IOLIST, ChunksVisitor)
+            .map(IoList)
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use crate::{from_reader, to_vec};
+
+    use super::{Binary, Charlist, IoData, IoList};
+
+    #[test]
+    fn binary() {
+        let expected = vec![0x6d, 0x00, 0x00, 0x00, 0x03, 0x01, 0x02, 0x03];
+        assert_eq!(expected, to_vec(&Binary(vec![1u8, 2, 3])).unwrap());
+        assert_eq!(expected, to_vec(&Binary(&[1u8, 2, 3][..])).unwrap());
+        assert_eq!(Binary(vec![1, 2, 3]), from_reader::<_, Binary>(expected.as_slice()).unwrap());
+        assert!(from_reader::<_, Binary>(&[0x6b, 0x00, 0x01, 0x01][..]).is_err());
+    }
+
+    #[test]
+    fn charlist() {
+        for (input, expected) in vec![
+            ("",    vec![0x6a]),
+            ("abc", vec![0x6b, 0x00, 0x03, 0x61, 0x62, 0x63]),
+            ("é",   vec![0x6b, 0x00, 0x01, 0xe9]),
+            ("aア", vec![0x6c, 0x00, 0x00, 0x00, 0x02, 0x61, 0x61, 0x62, 0x00, 0x00, 0x30, 0xa2, 0x6a]),
+        ] {
+            assert_eq!(expected, to_vec(&Charlist(input)).unwrap(), "{:?}", input);
+            let actual: Charlist = from_reader(expected.as_slice()).unwrap();
+            assert_eq!(input, actual.0, "{:?}", input);
+        }
+        assert!(from_reader::<_, Charlist>(&[0x6c, 0x00, 0x00, 0x00, 0x01, 0x61, 0x61, 0x61, 0x62][..]).is_err());
+        assert!(from_reader::<_, Charlist>(&[0x6d, 0x00, 0x00, 0x00, 0x00][..]).is_err());
+    }
+
+    #[test]
+    fn iodata() {
+        assert_eq!(vec![0x6d, 0x00, 0x00, 0x00, 0x01, 0x61], to_vec(&IoData("a")).unwrap());
+        for input in vec![
+            vec![0x6d, 0x00, 0x00, 0x00, 0x02, 0x61, 0x62],
+            vec![0x6b, 0x00, 0x02, 0x61, 0x62],
+            vec![0x6c, 0x00, 0x00, 0x00, 0x02, 0x61, 0x61, 0x61, 0x62, 0x6a],
+        ] {
+            let actual: IoData = from_reader(input.as_slice()).unwrap();
+            assert_eq!(b"ab", actual.0.as_slice(), "{:?}", input);
+        }
+        assert_eq!(IoData(vec![]), from_reader::<_, IoData>(&[0x6a][..]).unwrap());
+        assert!(from_reader::<_, IoData>(&[0x6c, 0x00, 0x00, 0x00, 0x01, 0x62, 0x00, 0x00, 0x01, 0x00, 0x6a][..]).is_err());
+    }
+
+    #[test]
+    fn iodata_nested() {
+        // [$a, [<<"bc">>, [] | <<>>], "de", $f | <<"gh">>]
+        let input = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x04,
+              0x61, 0x61,
+              0x6c, 0x00, 0x00, 0x00, 0x02,
+                0x6d, 0x00, 0x00, 0x00, 0x02, 0x62, 0x63,
+                0x6a,
+              0x6d, 0x00, 0x00, 0x00, 0x00,
+              0x6b, 0x00, 0x02, 0x64, 0x65,
+              0x61, 0x66,
+            0x6d, 0x00, 0x00, 0x00, 0x02, 0x67, 0x68,
+        ];
+        let actual: IoData = from_reader(input.as_slice()).unwrap();
+        assert_eq!(b"abcdefgh", actual.0.as_slice());
+
+        let actual: IoList = from_reader(input.as_slice()).unwrap();
+        assert_eq!(vec![b"a".to_vec(), b"bc".to_vec(), b"def".to_vec(), b"gh".to_vec()], actual.0);
+
+        for input in vec![
+            vec![0x61, 0x01],                                                 // a bare byte
+            vec![0x6c, 0x00, 0x00, 0x00, 0x01, 0x61, 0x01, 0x61, 0x02],       // byte tail
+            vec![0x6c, 0x00, 0x00, 0x00, 0x01, 0x64, 0x00, 0x01, 0x61, 0x6a], // atom
+        ] {
+            assert!(from_reader::<_, IoData>(input.as_slice()).is_err(), "{:?}", input);
+            assert!(from_reader::<_, IoList>(input.as_slice()).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn iolist() {
+        let input = IoList(vec![&b"ab"[..], &b""[..]]);
+        let expected = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x02,
+              0x6d, 0x00, 0x00, 0x00, 0x02, 0x61, 0x62,
+              0x6d, 0x00, 0x00, 0x00, 0x00,
+            0x6a,
+        ];
+        assert_eq!(expected, to_vec(&input).unwrap());
+        let actual: IoList = from_reader(expected.as_slice()).unwrap();
+        assert_eq!(vec![b"ab".to_vec()], actual.0);
+        let actual: IoData = from_reader(expected.as_slice()).unwrap();
+        assert_eq!(b"ab", actual.0.as_slice());
+    }
+}
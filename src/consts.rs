@@ -9,7 +9,7 @@ pub const ERL_INTEGER_EXT         :u8 = 0x62; //  98: 'b'
 //  const ERL_FLOAT_EXT           :u8 = 0x63; //  99: 'c'
 pub const NEW_FLOAT_EXT           :u8 = 0x46; //  70: 'F'
 pub const ERL_ATOM_EXT            :u8 = 0x64; // 100: 'd'
-//  const ERL_SMALL_ATOM_EXT      :u8 = 0x73; // 115: 's'
+pub const ERL_SMALL_ATOM_EXT      :u8 = 0x73; // 115: 's'
 pub const ERL_ATOM_UTF8_EXT       :u8 = 0x76; // 118: 'v'
 pub const ERL_SMALL_ATOM_UTF8_EXT :u8 = 0x77; // 119: 'w'
 //  const ERL_REFERENCE_EXT       :u8 = 0x65; // 101: 'e'
@@ -27,7 +27,7 @@ pub const ERL_LIST_EXT            :u8 = 0x6c; // 108: 'l'
 pub const ERL_BINARY_EXT          :u8 = 0x6d; // 109: 'm'
 //  const ERL_BIT_BINARY_EXT      :u8 = 0x4d; //  77: 'M'
 pub const ERL_SMALL_BIG_EXT       :u8 = 0x6e; // 110: 'n'
-//  const ERL_LARGE_BIG_EXT       :u8 = 0x6f; // 111: 'o',
+pub const ERL_LARGE_BIG_EXT       :u8 = 0x6f; // 111: 'o',
 //  const ERL_NEW_FUN_EXT         :u8 = 0x70; // 112: 'p'
 pub const ERL_MAP_EXT             :u8 = 0x74; // 116: 't'
 //  const ERL_FUN_EXT             :u8 = 0x75; // 117: 'u'
@@ -1,15 +1,25 @@
+use std::collections::HashSet;
 use std::io;
+use std::marker::PhantomData;
 
 use serde::{de, serde_if_integer128};
 
+use crate::binary::{CHARLIST, IODATA, IOLIST};
 use crate::consts::*;
+use crate::dec::Decoder;
+use crate::elixir::ELIXIR_STRUCT;
 use crate::error::Error;
 use crate::io::{Number, Reader};
+use crate::proplist::PROPLIST;
+use crate::raw::RAW_TERM;
 
 pub struct Deserializer<R> {
     reader: Reader<R>,
     etype: Vec<[u8; 2]>,
     ref_n: Option<usize>,
+    elixir: bool,
+    some: bool,       // the next option is known to be present; compounds clear it
+    scratch: Vec<u8>, // atom names handed to visit_str, reused
 }
 
 impl<R> Deserializer<R>
@@ -21,36 +31,71 @@ where
             reader: Reader::new(r),
             etype: Vec::with_capacity(16),
             ref_n: None,
+            elixir: false,
+            some: false,
+            scratch: Vec::new(),
         }
     }
 
+    // elixir conventions: nil for None
+    pub fn elixir(mut self, b: bool) -> Self {
+        self.elixir = b;
+        self
+    }
+
     pub fn read_number(&mut self) -> Result<Number, Error> {
-        match self.reader.read_u8()? {
-            ERL_SMALL_INTEGER_EXT => self.reader.read_u8().map(Number::U8),
-            ERL_INTEGER_EXT => self.reader.read_i32().map(Number::I32),
-            ERL_SMALL_BIG_EXT => self.read_small_big(),
-            u => Err(invalid_data!("read_number: {}", u)),
+        self.reader.read_number()
+    }
+
+    // nothing may follow the term
+    pub(crate) fn end(&mut self) -> Result<(), Error> {
+        match self.reader.read_opt_u8()? {
+            None => Ok(()),
+            Some(u) => Err(invalid_data!("end: {}", u)),
         }
     }
 
-    fn read_small_big(&mut self) -> Result<Number, Error> {
-        match self.reader.read_u8()? {
-            a if a <= 8 => {
-                let s = self.reader.read_u8()?;
-                let mut n = 0u64;
-                for i in 0..a {
-                    let u = self.reader.read_u8()?;
-                    n |= (u as u64) << (i * 8);
-                }
-                Ok(Number::SmallBig(n, s))
-            }
-            a => Err(invalid_data!("read_small_big: {}", a)),
+    // elements of a list, decoded one at a time as they are read
+    pub fn iter_list<T>(&mut self) -> Result<ListIter<'_, R, T>, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        let (len, string) = match self.reader.read_u8()? {
+            ERL_NIL_EXT => (None, false),
+            ERL_LIST_EXT => (self.reader.read_u32().map(|u| Some(u as usize))?, false),
+            ERL_STRING_EXT => (self.reader.read_u16().map(|u| Some(u as usize))?, true),
+            u => return Err(invalid_data!("iter_list: {}", u)),
+        };
+        Ok(ListIter {
+            access: ListAccess { de: self, len },
+            string,
+            failed: false,
+            output: PhantomData,
+        })
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter<T>(self) -> StreamDeserializer<R, T>
+    where
+        T: de::DeserializeOwned,
+    {
+        StreamDeserializer {
+            de: self,
+            magic: false,
+            packet: 0,
+            failed: false,
+            output: PhantomData,
         }
     }
 
     #[rustfmt::skip]
     fn read_tuple(&mut self) -> Result<usize, Error> {
-        match self.reader.read_u8()? {
+        self.reader.read_u8().and_then(|u| self.read_tuple_ext(u))
+    }
+
+    #[rustfmt::skip]
+    fn read_tuple_ext(&mut self, tag: u8) -> Result<usize, Error> {
+        match tag {
             ERL_SMALL_TUPLE_EXT => {
                 self.reader.read_u8().map(|u| u as usize)
             }
@@ -73,6 +118,128 @@ where
         }
     }
 
+    fn read_atom(&mut self) -> Result<String, Error> {
+        self.reader.read_u8().and_then(|u| self.read_atom_ext(u))
+    }
+
+    #[rustfmt::skip]
+    fn read_atom_ext(&mut self, tag: u8) -> Result<String, Error> {
+        match tag {
+            ERL_ATOM_EXT            => self.reader.read_latin1_u16(),
+            ERL_SMALL_ATOM_EXT      => self.reader.read_latin1_u8(),
+            ERL_ATOM_UTF8_EXT       => self.reader.read_string_u16(),
+            ERL_SMALL_ATOM_UTF8_EXT => self.reader.read_string_u8(),
+            u                       => Err(invalid_data!("read_atom: {}", u)),
+        }
+    }
+
+    // as read_atom, into scratch: only a latin-1 name beyond ASCII allocates
+    #[rustfmt::skip]
+    fn read_atom_scratch(&mut self) -> Result<&str, Error> {
+        let (latin1, len) = match self.reader.read_u8()? {
+            ERL_ATOM_EXT            => (true, self.reader.read_u16()? as usize),
+            ERL_SMALL_ATOM_EXT      => (true, self.reader.read_u8()? as usize),
+            ERL_ATOM_UTF8_EXT       => (false, self.reader.read_u16()? as usize),
+            ERL_SMALL_ATOM_UTF8_EXT => (false, self.reader.read_u8()? as usize),
+            u                       => return Err(invalid_data!("read_atom: {}", u)),
+        };
+        self.scratch.resize(len, 0);
+        self.reader.read_exact(&mut self.scratch)?;
+        if latin1 && !self.scratch.is_ascii() {
+            self.scratch = self.scratch.iter().map(|&u| u as char).collect::<String>().into_bytes();
+        }
+        std::str::from_utf8(&self.scratch).map_err(|e| invalid_data!("read_atom: {}", e))
+    }
+
+    fn is_proplist(&mut self) -> bool {
+        match self.etype.last() {
+            Some([ERL_LIST_EXT, 0]) => self.etype.pop().is_some(),
+            _ => false,
+        }
+    }
+
+    fn read_charlist(&mut self) -> Result<String, Error> {
+        self.reader
+            .read_u8()
+            .and_then(|u| self.read_charlist_ext(u))
+    }
+
+    #[rustfmt::skip]
+    fn read_charlist_ext(&mut self, tag: u8) -> Result<String, Error> {
+        match tag {
+            ERL_NIL_EXT => {
+                Ok(String::new())
+            }
+            ERL_STRING_EXT => {
+                self.reader.read_exact_u16().map(|v| v.iter().map(|&u| u as char).collect())
+            }
+            ERL_LIST_EXT => {
+                let n = self.reader.read_u32()? as usize;
+                let mut s = String::with_capacity(n);
+                for _ in 0..n {
+                    let c = match self.read_number()? {
+                        Number::U8(u) => Some(u as char),
+                        Number::I32(i) => std::char::from_u32(i as u32),
+                        _ => None,
+                    };
+                    s.push(c.ok_or_else(|| invalid_data!("read_charlist: {}", s))?);
+                }
+                self.read_unit().map(|()| s)
+            }
+            u => Err(invalid_data!("read_charlist: {}", u)),
+        }
+    }
+
+    // a binary, or a possibly improper list of bytes, binaries and iolists with
+    //  a binary or [] tail; ends marks where each binary or run of bytes stops in vec
+    fn read_iodata(&mut self, vec: &mut Vec<u8>, ends: &mut Vec<usize>) -> Result<(), Error> {
+        fn close(vec: &[u8], ends: &mut Vec<usize>) {
+            if ends.last().copied().unwrap_or(0) < vec.len() {
+                ends.push(vec.len());
+            }
+        }
+        let mut stack: Vec<usize> = Vec::new(); // items left per list, tail included
+        let mut top = true;
+        loop {
+            let tail = match stack.last_mut() {
+                Some(u) => {
+                    *u -= 1;
+                    *u == 0
+                }
+                None if top => false,
+                None => break,
+            };
+            match (self.reader.read_u8()?, tail) {
+                (ERL_NIL_EXT, _) => (),
+                (ERL_BINARY_EXT, _) => {
+                    close(vec, ends);
+                    let n = self.reader.read_u32()? as usize;
+                    let from = vec.len();
+                    vec.resize(from + n, 0);
+                    self.reader.read_exact(&mut vec[from..])?;
+                    close(vec, ends);
+                }
+                (ERL_SMALL_INTEGER_EXT, false) if !top => {
+                    vec.push(self.reader.read_u8()?);
+                }
+                (ERL_STRING_EXT, false) => {
+                    vec.append(&mut self.reader.read_exact_u16()?);
+                }
+                (ERL_LIST_EXT, false) => {
+                    let n = self.reader.read_u32()? as usize;
+                    stack.push(n + 1);
+                }
+                (u, _) => return Err(invalid_data!("read_iodata: {}", u)),
+            }
+            top = false;
+            while stack.last() == Some(&0) {
+                stack.pop();
+            }
+        }
+        close(vec, ends);
+        Ok(())
+    }
+
     pub fn read_unit(&mut self) -> Result<(), Error> {
         match self.reader.read_u8()? {
             ERL_NIL_EXT => Ok(()),
@@ -81,7 +248,7 @@ where
     }
 }
 
-impl<'de, 'a, R> de::Deserializer<'de> for &'a mut Deserializer<R>
+impl<'de, R> de::Deserializer<'de> for &mut Deserializer<R>
 where
     R: io::Read,
 {
@@ -94,20 +261,12 @@ where
         unimplemented!("deserialize_any")
     }
 
-    #[rustfmt::skip]
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        match self.reader.read_u8()? {
-            ERL_ATOM_EXT | ERL_ATOM_UTF8_EXT => {
-                self.reader.read_string_u16().and_then(|s| visitor.visit_bool(s == "true"))
-            }
-            ERL_SMALL_ATOM_UTF8_EXT => {
-                self.reader.read_string_u8().and_then(|s| visitor.visit_bool(s == "true"))
-            }
-            u => Err(invalid_data!("deserialize_bool: {}", u)),
-        }
+        self.read_atom()
+            .and_then(|s| visitor.visit_bool(s == "true"))
     }
 
     #[rustfmt::skip]
@@ -153,7 +312,7 @@ where
             Number::U8(u)                                  => visitor.visit_i32(u as i32),
             Number::I32(i)                                 => visitor.visit_i32(i),
             Number::SmallBig(u, 0) if u <= i32::MAX as u64 => visitor.visit_i32(u as i32),
-            Number::SmallBig(u, 1) if u <= i32::MAX as u64 => visitor.visit_i32(u as i32 * -1),
+            Number::SmallBig(u, 1) if u <= i32::MAX as u64 => visitor.visit_i32(-(u as i32)),
             e                                              => Err(invalid_data!("deserialize_i32: {:?}", e)),
         }
     }
@@ -167,7 +326,7 @@ where
             Number::U8(u)                                  => visitor.visit_i64(u as i64),
             Number::I32(i)                                 => visitor.visit_i64(i as i64),
             Number::SmallBig(u, 0) if u <= i64::MAX as u64 => visitor.visit_i64(u as i64),
-            Number::SmallBig(u, 1) if u <= i64::MAX as u64 => visitor.visit_i64(u as i64 * -1),
+            Number::SmallBig(u, 1) if u <= i64::MAX as u64 => visitor.visit_i64(-(u as i64)),
             e                                              => Err(invalid_data!("deserialize_i64: {:?}", e)),
         }
     }
@@ -280,11 +439,12 @@ where
         Err(unsupported!("deserialize_char"))
     }
 
-    fn deserialize_str<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    // an atom, by name
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(unsupported!("deserialize_str"))
+        self.read_atom_scratch().and_then(|s| visitor.visit_str(s))
     }
 
     #[rustfmt::skip]
@@ -295,16 +455,18 @@ where
         match self.etype.pop() {
             None => {
                 match self.reader.read_u8()? {
-                    ERL_NIL_EXT => {
-                        visitor.visit_str("")
-                    }
-                    ERL_STRING_EXT => {
-                        self.reader.read_string_u16().and_then(|s| visitor.visit_string(s))
-                    }
-                    u => Err(invalid_data!("deserialize_string: {}", u)),
+                    ERL_BINARY_EXT => self.reader.read_string_u32(),
+                    u => self.read_charlist_ext(u),
                 }
+                .and_then(|s| visitor.visit_string(s))
+            }
+            Some([ERL_ATOM_EXT, _]) => {
+                self.reader.read_latin1_u16().and_then(|s| visitor.visit_string(s))
             }
-            Some([ERL_ATOM_EXT, _]) | Some([ERL_ATOM_UTF8_EXT, _]) => {
+            Some([ERL_SMALL_ATOM_EXT, _]) => {
+                self.reader.read_latin1_u8().and_then(|s| visitor.visit_string(s))
+            }
+            Some([ERL_ATOM_UTF8_EXT, _]) => {
                 self.reader.read_string_u16().and_then(|s| visitor.visit_string(s))
             }
             Some([ERL_SMALL_ATOM_UTF8_EXT, _]) => {
@@ -340,6 +502,21 @@ where
     {
         match self.ref_n {
             Some(_) => visitor.visit_some(self),
+            None if std::mem::take(&mut self.some) => visitor.visit_some(self),
+            None if self.elixir => {
+                // read whole to tell nil from a value, which is then decoded from the copy
+                let mut vec = Vec::new();
+                self.reader.read_term(&mut vec)?;
+                let nil = Decoder::new(&vec)
+                    .decode_atom()
+                    .is_ok_and(|a| a.as_str() == "nil");
+                if nil {
+                    return visitor.visit_none();
+                }
+                let mut de = Deserializer::new(vec.as_slice()).elixir(true);
+                let t = visitor.visit_some(&mut de)?;
+                de.end().map(|()| t)
+            }
             None => visitor.visit_none(),
         }
     }
@@ -370,6 +547,43 @@ where
     where
         V: de::Visitor<'de>,
     {
+        self.some = false;
+        match name {
+            RAW_TERM => {
+                let mut vec = Vec::new();
+                self.reader.read_term(&mut vec)?;
+                return visitor.visit_byte_buf(vec);
+            }
+            CHARLIST => return self.read_charlist().and_then(|s| visitor.visit_string(s)),
+            ELIXIR_STRUCT => return visitor.visit_newtype_struct(self),
+            IODATA => {
+                let mut vec = Vec::new();
+                self.read_iodata(&mut vec, &mut Vec::new())?;
+                return visitor.visit_byte_buf(vec);
+            }
+            PROPLIST => {
+                self.etype.push([ERL_LIST_EXT, 0]);
+                let value = visitor.visit_newtype_struct(&mut *self)?;
+                return match self.is_proplist() {
+                    true => Err(invalid_input!("deserialize_newtype_struct: {}", name)),
+                    false => Ok(value),
+                };
+            }
+            IOLIST => {
+                let (mut vec, mut ends) = (Vec::new(), Vec::new());
+                self.read_iodata(&mut vec, &mut ends)?;
+                let mut from = 0;
+                let chunks = ends.iter().map(|&u| {
+                    let v = &vec[from..u];
+                    from = u;
+                    v
+                });
+                let mut seq = de::value::SeqDeserializer::new(chunks);
+                let value = visitor.visit_seq(&mut seq)?;
+                return seq.end().map(|()| value);
+            }
+            _ => (),
+        }
         match self.read_tuple()? {
             1 => visitor.visit_newtype_struct(self),
             u => Err(interrupted!("deserialize_newtype_struct: {}, {}", name, u)),
@@ -381,6 +595,7 @@ where
     where
         V: de::Visitor<'de>,
     {
+        self.some = false;
         match self.etype.pop() {
             None => {
                 match self.reader.read_u8()? {
@@ -426,6 +641,7 @@ where
     where
         V: de::Visitor<'de>,
     {
+        self.some = false;
         match self.read_tuple()? {
             u if u == len => {
                 visitor.visit_seq(ListAccess {
@@ -447,6 +663,7 @@ where
     where
         V: de::Visitor<'de>,
     {
+        self.some = false;
         match self.read_tuple()? {
             u if u == len => {
                 visitor.visit_seq(ListAccess {
@@ -463,6 +680,11 @@ where
     where
         V: de::Visitor<'de>,
     {
+        self.some = false;
+        if self.is_proplist() {
+            let tag = self.reader.read_u8()?;
+            return visitor.visit_map(PropAccess::new(self, tag)?);
+        }
         match self.reader.read_u8()? {
             ERL_MAP_EXT => {
                 match self.reader.read_u32()? as usize {
@@ -470,12 +692,14 @@ where
                         visitor.visit_map(MapAccess {
                             de: self,
                             len: None,
+                            keys: false,
                         })
                     }
                     u => {
                         visitor.visit_map(MapAccess {
                             de: self,
                             len: Some(u),
+                            keys: false,
                         })
                     }
                 }
@@ -494,6 +718,7 @@ where
     where
         V: de::Visitor<'de>,
     {
+        self.some = false;
         match name.len() {
             0 => {
                 visitor.visit_seq(ListAccess {
@@ -501,14 +726,27 @@ where
                     len: Some(fields.len()),
                 })
             }
-            _ => match self.read_tuple()? {
-                u if u == fields.len() => {
-                    visitor.visit_seq(ListAccess {
+            _ => match self.reader.read_u8()? {
+                u if self.is_proplist() => {
+                    visitor.visit_map(PropAccess::new(self, u)?)
+                }
+                ERL_MAP_EXT => {
+                    let u = self.reader.read_u32()? as usize;
+                    visitor.visit_map(MapAccess {
                         de: self,
-                        len: Some(u),
+                        len: if u > 0 { Some(u) } else { None },
+                        keys: true,
                     })
                 }
-                u => Err(interrupted!("deserialize_struct: {}, {}", name, u)),
+                u => match self.read_tuple_ext(u)? {
+                    u if u == fields.len() => {
+                        visitor.visit_seq(ListAccess {
+                            de: self,
+                            len: Some(u),
+                        })
+                    }
+                    u => Err(interrupted!("deserialize_struct: {}, {}", name, u)),
+                },
             },
         }
     }
@@ -522,6 +760,7 @@ where
     where
         V: de::Visitor<'de>,
     {
+        self.some = false;
         visitor.visit_enum(self)
     }
 
@@ -535,6 +774,10 @@ where
                 self.etype.push([ERL_ATOM_EXT, 0]);
                 visitor.visit_str("Latin1")
             }
+            ERL_SMALL_ATOM_EXT => {
+                self.etype.push([ERL_SMALL_ATOM_EXT, 0]);
+                visitor.visit_str("Latin1")
+            }
             ERL_SMALL_ATOM_UTF8_EXT => {
                 self.etype.push([ERL_SMALL_ATOM_UTF8_EXT, 0]);
                 visitor.visit_str("UTF8Small")
@@ -569,11 +812,13 @@ where
         }
     }
 
-    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        unimplemented!("deserialize_ignored_any");
+        let mut vec = Vec::new();
+        self.reader.read_term(&mut vec)?;
+        visitor.visit_unit()
     }
 
     fn is_human_readable(&self) -> bool {
@@ -581,7 +826,7 @@ where
     }
 }
 
-impl<'de, 'a, R> de::EnumAccess<'de> for &'a mut Deserializer<R>
+impl<'de, R> de::EnumAccess<'de> for &mut Deserializer<R>
 where
     R: io::Read,
 {
@@ -596,7 +841,7 @@ where
     }
 }
 
-impl<'de, 'a, R> de::VariantAccess<'de> for &'a mut Deserializer<R>
+impl<'de, R> de::VariantAccess<'de> for &mut Deserializer<R>
 where
     R: io::Read,
 {
@@ -665,6 +910,54 @@ where
     }
 }
 
+pub struct ListIter<'a, R, T> {
+    access: ListAccess<'a, R>,
+    string: bool,
+    failed: bool,
+    output: PhantomData<T>,
+}
+
+impl<R, T> ListIter<'_, R, T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    fn next_string(&mut self) -> Result<Option<T>, Error> {
+        match self.access.len {
+            Some(u) if u > 0 => {
+                self.access.len = Some(u - 1);
+                let u = self.access.de.reader.read_u8()?;
+                T::deserialize(de::IntoDeserializer::<Error>::into_deserializer(u)).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<R, T> Iterator for ListIter<'_, R, T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let r = match self.string {
+            true => self.next_string(),
+            false => de::SeqAccess::next_element(&mut self.access),
+        };
+        self.failed = r.is_err();
+        r.transpose()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.access.len)
+    }
+}
+
 struct ArrayAccess<'a, R> {
     de: &'a mut Deserializer<R>,
     len: Option<usize>,
@@ -697,6 +990,7 @@ where
 struct MapAccess<'a, R> {
     de: &'a mut Deserializer<R>,
     len: Option<usize>,
+    keys: bool, // struct fields, keyed by atom
 }
 
 impl<'de, 'a, R> de::MapAccess<'de> for MapAccess<'a, R>
@@ -710,6 +1004,12 @@ where
         K: de::DeserializeSeed<'de>,
     {
         match self.len {
+            Some(u) if self.keys => {
+                self.len = if u > 1 { Some(u - 1) } else { None };
+                let s = self.de.read_atom()?;
+                seed.deserialize(de::IntoDeserializer::<Error>::into_deserializer(s))
+                    .map(Some)
+            }
             Some(u) => {
                 self.len = if u > 1 { Some(u - 1) } else { None };
                 seed.deserialize(&mut *self.de).map(Some)
@@ -730,6 +1030,98 @@ where
     }
 }
 
+// [{Key, Value} | Key, ...] as proplists:get_value/2 sees it: a bare atom is
+//  {Key, true} and only the first occurrence of a key counts
+struct PropAccess<'a, R> {
+    de: &'a mut Deserializer<R>,
+    len: Option<usize>,
+    seen: HashSet<String>,
+    bare: bool,
+}
+
+impl<'a, R> PropAccess<'a, R>
+where
+    R: io::Read,
+{
+    fn new(de: &'a mut Deserializer<R>, tag: u8) -> Result<Self, Error> {
+        let len = match tag {
+            ERL_NIL_EXT => None,
+            ERL_LIST_EXT => Some(de.reader.read_u32()? as usize),
+            u => return Err(invalid_data!("proplist: {}", u)),
+        };
+        Ok(PropAccess {
+            de,
+            len,
+            seen: HashSet::new(),
+            bare: false,
+        })
+    }
+
+    fn next_key(&mut self) -> Result<Option<String>, Error> {
+        loop {
+            match self.len {
+                Some(0) => {
+                    self.len = None;
+                    match self.de.reader.read_u8()? {
+                        ERL_NIL_EXT => return Ok(None),
+                        u => return Err(invalid_data!("proplist: {}", u)),
+                    }
+                }
+                Some(u) => self.len = Some(u - 1),
+                None => return Ok(None),
+            }
+            let (key, bare) = match self.de.reader.read_u8()? {
+                ERL_SMALL_TUPLE_EXT => match self.de.reader.read_u8()? {
+                    2 => (self.de.read_atom()?, false),
+                    u => return Err(invalid_data!("proplist: {{{}}}", u)),
+                },
+                u => (self.de.read_atom_ext(u)?, true),
+            };
+            if self.seen.insert(key.clone()) {
+                self.bare = bare;
+                return Ok(Some(key));
+            }
+            if !bare {
+                self.de.reader.read_term(&mut Vec::new())?;
+            }
+        }
+    }
+}
+
+impl<'de, 'a, R> de::MapAccess<'de> for PropAccess<'a, R>
+where
+    R: io::Read,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.next_key()? {
+            Some(s) => seed
+                .deserialize(de::IntoDeserializer::<Error>::into_deserializer(s))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.bare {
+            true => seed.deserialize(de::IntoDeserializer::<Error>::into_deserializer(true)),
+            false => {
+                self.de.some = true;
+                let value = seed.deserialize(&mut *self.de);
+                self.de.some = false;
+                value
+            }
+        }
+    }
+}
+
 pub fn from_reader<R, T>(reader: R) -> Result<T, Error>
 where
     R: io::Read,
@@ -739,8 +1131,101 @@ where
     de::Deserialize::deserialize(&mut de)
 }
 
+// consecutive terms, each optionally preceded by a {packet,N} length
+//  and/or the version magic; ends at a clean end of stream
+pub struct StreamDeserializer<R, T> {
+    de: Deserializer<R>,
+    magic: bool,
+    packet: usize,
+    failed: bool,
+    output: PhantomData<T>,
+}
+
+impl<R, T> StreamDeserializer<R, T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    pub fn magic(mut self, b: bool) -> Self {
+        self.magic = b;
+        self
+    }
+
+    // 0 (none), 1, 2 or 4
+    pub fn packet(mut self, n: usize) -> Self {
+        self.packet = n;
+        self
+    }
+
+    fn next_term(&mut self) -> Result<Option<T>, Error> {
+        let u = match self.de.reader.read_opt_u8()? {
+            Some(u) => u,
+            None => return Ok(None),
+        };
+        match (self.packet, self.magic) {
+            (0, true) => match u {
+                ERL_VERSION_MAGIC => de::Deserialize::deserialize(&mut self.de).map(Some),
+                u => Err(invalid_data!("next: {}", u)),
+            },
+            (0, false) => {
+                let mut vec = Vec::new();
+                self.de.reader.read_term_after(u, &mut vec)?;
+                from_slice(&vec, false).map(Some)
+            }
+            (n @ 1, _) | (n @ 2, _) | (n @ 4, _) => {
+                let len = self
+                    .de
+                    .reader
+                    .read_exact_usize(n - 1)?
+                    .iter()
+                    .fold(u as usize, |a, &b| (a << 8) | b as usize);
+                let vec = self.de.reader.read_exact_usize(len)?;
+                from_slice(&vec, self.magic).map(Some)
+            }
+            (n, _) => Err(invalid_input!("packet: {}", n)),
+        }
+    }
+}
+
+impl<R, T> Iterator for StreamDeserializer<R, T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let r = self.next_term();
+        self.failed = r.is_err();
+        r.transpose()
+    }
+}
+
+// the whole slice must be exactly one term
+pub(crate) fn from_slice<T>(v: &[u8], magic: bool) -> Result<T, Error>
+where
+    T: de::DeserializeOwned,
+{
+    let mut de = Deserializer::new(v);
+    if magic {
+        match de.reader.read_u8()? {
+            ERL_VERSION_MAGIC => (),
+            u => return Err(invalid_data!("from_slice: {}", u)),
+        }
+    }
+    let t = de::Deserialize::deserialize(&mut de)?;
+    match de.reader.get_ref().len() {
+        0 => Ok(t),
+        u => Err(invalid_data!("from_slice: {}", u)),
+    }
+}
+
 #[cfg(test)]
 #[rustfmt::skip]
+#[allow(clippy::identity_op, clippy::unnecessary_cast, clippy::useless_vec)]
 mod tests {
 
     use std::collections::HashMap;
@@ -750,6 +1235,7 @@ mod tests {
 
     use crate::error::Error;
     use crate::i27;
+    use crate::io::Number;
     use crate::term;
 
     #[derive(Deserialize, PartialEq, Debug)]
@@ -977,6 +1463,8 @@ mod tests {
         for (expected, input) in vec![
             ("",      vec![0x6a]),
             ("hello", vec![0x6b, 0x00, 0x05, 0x68, 0x65, 0x6c, 0x6c, 0x6f]),
+            ("café",  vec![0x6b, 0x00, 0x04, 0x63, 0x61, 0x66, 0xe9]),
+            ("aア",   vec![0x6c, 0x00, 0x00, 0x00, 0x02, 0x61, 0x61, 0x62, 0x00, 0x00, 0x30, 0xa2, 0x6a]),
         ] {
             let actual: Result<String, Error> = test!(&input);
             assert!(actual.is_ok(), "{:?}", actual);
@@ -1095,6 +1583,14 @@ mod tests {
                 term::Atom::Latin1("n".to_owned()),
                 vec![0x64, 0x00, 0x01, 0x6e],
             ),
+            (
+                term::Atom::Latin1("café".to_owned()),
+                vec![0x64, 0x00, 0x04, 0x63, 0x61, 0x66, 0xe9],
+            ),
+            (
+                term::Atom::Latin1("café".to_owned()),
+                vec![0x73, 0x04, 0x63, 0x61, 0x66, 0xe9],
+            ),
             (
                 term::Atom::UTF8Small("n".to_owned()),
                 vec![0x77, 0x01, 0x6e],
@@ -1488,4 +1984,84 @@ mod tests {
             assert_eq!(expected, actual.unwrap(), "{:?}", input);
         }
     }
+
+    #[test]
+    fn into_iter() {
+        for (magic, packet, input) in vec![
+            (false, 0, vec![0x61, 0x01, 0x62, 0x00, 0x00, 0x01, 0x00, 0x61, 0x03]),
+            (true,  0, vec![0x83, 0x61, 0x01, 0x83, 0x62, 0x00, 0x00, 0x01, 0x00, 0x83, 0x61, 0x03]),
+            (false, 1, vec![0x02, 0x61, 0x01, 0x05, 0x62, 0x00, 0x00, 0x01, 0x00, 0x02, 0x61, 0x03]),
+            (true,  2, vec![0x00, 0x03, 0x83, 0x61, 0x01,
+                            0x00, 0x06, 0x83, 0x62, 0x00, 0x00, 0x01, 0x00,
+                            0x00, 0x03, 0x83, 0x61, 0x03]),
+            (false, 4, vec![0x00, 0x00, 0x00, 0x02, 0x61, 0x01,
+                            0x00, 0x00, 0x00, 0x05, 0x62, 0x00, 0x00, 0x01, 0x00,
+                            0x00, 0x00, 0x00, 0x02, 0x61, 0x03]),
+            (false, 0, vec![]),
+        ] {
+            let actual = super::Deserializer::new(input.as_slice())
+                .into_iter::<u16>()
+                .magic(magic)
+                .packet(packet)
+                .collect::<Result<Vec<_>, Error>>();
+            let expected = if input.is_empty() { vec![] } else { vec![1, 256, 3] };
+            assert_eq!(expected, actual.unwrap(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn into_iter_error() {
+        for (magic, packet, input) in vec![
+            (false, 0, vec![0x61, 0x01, 0x6b, 0x00]),       // truncated
+            (true,  0, vec![0x83, 0x61, 0x01, 0x61, 0x02]), // missing magic
+            (false, 1, vec![0x01, 0x61, 0x01, 0x6a]),       // frame too short
+            (false, 1, vec![0x03, 0x61, 0x01, 0x6a]),       // trailing bytes
+            (false, 3, vec![0x02, 0x61, 0x01]),
+        ] {
+            let mut iter = super::Deserializer::new(input.as_slice())
+                .into_iter::<u8>()
+                .magic(magic)
+                .packet(packet);
+            let actual = iter.by_ref().collect::<Result<Vec<_>, Error>>();
+            assert!(actual.is_err(), "{:?}", input);
+            assert!(iter.next().is_none(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn iter_list() {
+        for (input, expected) in vec![
+            (vec![0x6a, 0x61, 0x09],                         vec![]),
+            (vec![0x6b, 0x00, 0x02, 0x01, 0x02, 0x61, 0x09], vec![1, 2]),
+            (
+                vec![
+                    0x6c, 0x00, 0x00, 0x00, 0x03,
+                      0x61, 0x01,
+                      0x62, 0x00, 0x00, 0x01, 0x00,
+                      0x61, 0x03,
+                    0x6a,
+                    0x61, 0x09,
+                ],
+                vec![1, 256, 3],
+            ),
+        ] {
+            let mut de = super::Deserializer::new(input.as_slice());
+            let actual = de.iter_list::<u16>().unwrap().collect::<Result<Vec<_>, Error>>();
+            assert_eq!(expected, actual.unwrap(), "{:?}", input);
+            assert!(matches!(de.read_number(), Ok(Number::U8(9))), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn iter_list_error() {
+        for input in vec![
+            vec![0x61, 0x01],
+            vec![0x6c, 0x00, 0x00, 0x00, 0x01, 0x61, 0x01, 0x61, 0x02], // improper
+            vec![0x6c, 0x00, 0x00, 0x00, 0x02, 0x61, 0x01],             // truncated
+        ] {
+            let mut de = super::Deserializer::new(input.as_slice());
+            let actual = de.iter_list::<u8>().and_then(|i| i.collect::<Result<Vec<_>, Error>>());
+            assert!(actual.is_err(), "{:?}", input);
+        }
+    }
 }
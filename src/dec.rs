@@ -0,0 +1,470 @@
+use crate::consts::*;
+use crate::error::Error;
+use crate::io::{Number, Reader};
+use crate::term::{Atom, Pid, Ref};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Tag {
+    SmallInteger,
+    Integer,
+    SmallBig,
+    LargeBig,
+    Float,
+    Atom,
+    Ref,
+    Port,
+    Pid,
+    SmallTuple,
+    LargeTuple,
+    Map,
+    Nil,
+    String,
+    List,
+    Binary,
+}
+
+// index-based decoding over a complete buffer, like ei_decode_*:
+//  on error the position is left unchanged
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Decoder { buf, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn set_position(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        self.buf.get(self.pos..).unwrap_or(&[])
+    }
+
+    fn with<T, F>(&mut self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Reader<&'a [u8]>) -> Result<T, Error>,
+    {
+        let mut reader = Reader::new(self.remaining());
+        let t = f(&mut reader)?;
+        self.pos = self.buf.len() - reader.get_ref().len();
+        Ok(t)
+    }
+
+    fn rewind<T, F>(&mut self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Self) -> Result<T, Error>,
+    {
+        let pos = self.pos;
+        let r = f(self);
+        if r.is_err() {
+            self.pos = pos;
+        }
+        r
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        match self.remaining().get(..len) {
+            Some(v) => {
+                self.pos += len;
+                Ok(v)
+            }
+            None => Err(invalid_data!("take: {}", len)),
+        }
+    }
+
+    pub fn decode_version(&mut self) -> Result<(), Error> {
+        self.with(|r| match r.read_u8()? {
+            ERL_VERSION_MAGIC => Ok(()),
+            u => Err(invalid_data!("decode_version: {}", u)),
+        })
+    }
+
+    // does not advance; len is the arity, element count or byte length
+    #[rustfmt::skip]
+    pub fn get_type(&self) -> Result<(Tag, usize), Error> {
+        let mut r = Reader::new(self.remaining());
+        match r.read_u8()? {
+            ERL_SMALL_INTEGER_EXT   => Ok((Tag::SmallInteger, 0)),
+            ERL_INTEGER_EXT         => Ok((Tag::Integer, 0)),
+            ERL_SMALL_BIG_EXT       => r.read_u8().map(|u| (Tag::SmallBig, u as usize)),
+            ERL_LARGE_BIG_EXT       => r.read_u32().map(|u| (Tag::LargeBig, u as usize)),
+            NEW_FLOAT_EXT           => Ok((Tag::Float, 0)),
+            ERL_ATOM_EXT            => r.read_u16().map(|u| (Tag::Atom, u as usize)),
+            ERL_SMALL_ATOM_EXT      => r.read_u8().map(|u| (Tag::Atom, u as usize)),
+            ERL_ATOM_UTF8_EXT       => r.read_u16().map(|u| (Tag::Atom, u as usize)),
+            ERL_SMALL_ATOM_UTF8_EXT => r.read_u8().map(|u| (Tag::Atom, u as usize)),
+            ERL_NEWER_REFERENCE_EXT => r.read_u16().map(|u| (Tag::Ref, u as usize)),
+            ERL_NEW_PORT_EXT        => Ok((Tag::Port, 0)),
+            ERL_V4_PORT_EXT         => Ok((Tag::Port, 0)),
+            ERL_NEW_PID_EXT         => Ok((Tag::Pid, 0)),
+            ERL_SMALL_TUPLE_EXT     => r.read_u8().map(|u| (Tag::SmallTuple, u as usize)),
+            ERL_LARGE_TUPLE_EXT     => r.read_u32().map(|u| (Tag::LargeTuple, u as usize)),
+            ERL_MAP_EXT             => r.read_u32().map(|u| (Tag::Map, u as usize)),
+            ERL_NIL_EXT             => Ok((Tag::Nil, 0)),
+            ERL_STRING_EXT          => r.read_u16().map(|u| (Tag::String, u as usize)),
+            ERL_LIST_EXT            => r.read_u32().map(|u| (Tag::List, u as usize)),
+            ERL_BINARY_EXT          => r.read_u32().map(|u| (Tag::Binary, u as usize)),
+            u                       => Err(invalid_data!("get_type: {}", u)),
+        }
+    }
+
+    #[rustfmt::skip]
+    pub fn decode_atom(&mut self) -> Result<Atom, Error> {
+        self.with(|r| match r.read_u8()? {
+            ERL_ATOM_EXT            => r.read_latin1_u16().map(Atom::Latin1),
+            ERL_SMALL_ATOM_EXT      => r.read_latin1_u8().map(Atom::Latin1),
+            ERL_ATOM_UTF8_EXT       => r.read_string_u16().map(Atom::UTF8),
+            ERL_SMALL_ATOM_UTF8_EXT => r.read_string_u8().map(Atom::UTF8Small),
+            u                       => Err(invalid_data!("decode_atom: {}", u)),
+        })
+    }
+
+    pub fn decode_boolean(&mut self) -> Result<bool, Error> {
+        self.rewind(|d| match d.decode_atom()? {
+            Atom::Latin1(s) | Atom::UTF8Small(s) | Atom::UTF8(s) => match s.as_str() {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => Err(invalid_data!("decode_boolean: {}", s)),
+            },
+        })
+    }
+
+    #[rustfmt::skip]
+    pub fn decode_long(&mut self) -> Result<i64, Error> {
+        self.with(|r| match r.read_number()? {
+            Number::U8(u)                                 => Ok(u as i64),
+            Number::I32(i)                                => Ok(i as i64),
+            Number::SmallBig(u, 0) if u <= i64::MAX as u64 => Ok(u as i64),
+            Number::SmallBig(u, 1) if u <= i64::MIN.unsigned_abs() => Ok((u as i64).wrapping_neg()),
+            n                                             => Err(invalid_data!("decode_long: {:?}", n)),
+        })
+    }
+
+    #[rustfmt::skip]
+    pub fn decode_ulong(&mut self) -> Result<u64, Error> {
+        self.with(|r| match r.read_number()? {
+            Number::U8(u)                  => Ok(u as u64),
+            Number::I32(i) if i >= 0       => Ok(i as u64),
+            Number::SmallBig(u, 0)         => Ok(u),
+            n                              => Err(invalid_data!("decode_ulong: {:?}", n)),
+        })
+    }
+
+    pub fn decode_double(&mut self) -> Result<f64, Error> {
+        self.with(|r| match r.read_u8()? {
+            NEW_FLOAT_EXT => r.read_f64(),
+            u => Err(invalid_data!("decode_double: {}", u)),
+        })
+    }
+
+    pub fn decode_tuple_header(&mut self) -> Result<usize, Error> {
+        self.with(|r| match r.read_u8()? {
+            ERL_SMALL_TUPLE_EXT => r.read_u8().map(|u| u as usize),
+            ERL_LARGE_TUPLE_EXT => r.read_u32().map(|u| u as usize),
+            u => Err(invalid_data!("decode_tuple_header: {}", u)),
+        })
+    }
+
+    // n > 0 is followed by n elements and a tail
+    pub fn decode_list_header(&mut self) -> Result<usize, Error> {
+        self.with(|r| match r.read_u8()? {
+            ERL_NIL_EXT => Ok(0),
+            ERL_LIST_EXT => r.read_u32().map(|u| u as usize),
+            u => Err(invalid_data!("decode_list_header: {}", u)),
+        })
+    }
+
+    pub fn decode_map_header(&mut self) -> Result<usize, Error> {
+        self.with(|r| match r.read_u8()? {
+            ERL_MAP_EXT => r.read_u32().map(|u| u as usize),
+            u => Err(invalid_data!("decode_map_header: {}", u)),
+        })
+    }
+
+    pub fn decode_binary(&mut self) -> Result<&'a [u8], Error> {
+        self.rewind(|d| {
+            let len = d.with(|r| match r.read_u8()? {
+                ERL_BINARY_EXT => r.read_u32().map(|u| u as usize),
+                u => Err(invalid_data!("decode_binary: {}", u)),
+            })?;
+            d.take(len)
+        })
+    }
+
+    // STRING_EXT, NIL_EXT or a proper list of bytes
+    pub fn decode_string(&mut self) -> Result<Vec<u8>, Error> {
+        self.rewind(Self::read_string)
+    }
+
+    fn read_string(&mut self) -> Result<Vec<u8>, Error> {
+        match self.get_type()? {
+            (Tag::String, _) => self.with(|r| r.read_u8().and_then(|_| r.read_exact_u16())),
+            (Tag::Nil, _) | (Tag::List, _) => {
+                let n = self.decode_list_header()?;
+                let vec = (0..n)
+                    .map(|_| match self.get_type()? {
+                        (Tag::SmallInteger, _) => self.take(2).map(|v| v[1]),
+                        t => Err(invalid_data!("decode_string: {:?}", t)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if n > 0 {
+                    self.decode_list_header().and_then(|u| match u {
+                        0 => Ok(()),
+                        u => Err(invalid_data!("decode_string: {}", u)),
+                    })?;
+                }
+                Ok(vec)
+            }
+            t => Err(invalid_data!("decode_string: {:?}", t)),
+        }
+    }
+
+    pub fn decode_pid(&mut self) -> Result<Pid, Error> {
+        self.rewind(Self::read_pid)
+    }
+
+    fn read_pid(&mut self) -> Result<Pid, Error> {
+        self.with(|r| match r.read_u8()? {
+            ERL_NEW_PID_EXT => Ok(()),
+            u => Err(invalid_data!("decode_pid: {}", u)),
+        })?;
+        let node = self.decode_atom()?;
+        self.with(|r| {
+            Ok(Pid {
+                node,
+                num: r.read_u32()?,
+                serial: r.read_u32()?,
+                creation: r.read_u32()?,
+            })
+        })
+    }
+
+    pub fn decode_ref(&mut self) -> Result<Ref, Error> {
+        self.rewind(Self::read_ref)
+    }
+
+    fn read_ref(&mut self) -> Result<Ref, Error> {
+        let len = self.with(|r| match r.read_u8()? {
+            ERL_NEWER_REFERENCE_EXT => r.read_i16(),
+            u => Err(invalid_data!("decode_ref: {}", u)),
+        })?;
+        if !range!(len, 0, 5, i16) {
+            return Err(invalid_data!("decode_ref: {}", len));
+        }
+        let node = self.decode_atom()?;
+        self.with(|r| {
+            let creation = r.read_u32()?;
+            let n = (0..len)
+                .map(|_| r.read_u32())
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Ref {
+                len,
+                node,
+                creation,
+                n: if len > 0 { Some(n) } else { None },
+            })
+        })
+    }
+
+    // skips one complete term without allocating
+    pub fn skip_term(&mut self) -> Result<(), Error> {
+        self.rewind(Self::skip)
+    }
+
+    #[rustfmt::skip]
+    fn skip(&mut self) -> Result<(), Error> {
+        let mut pending = 1usize;
+        while pending > 0 {
+            pending -= 1;
+            let (tag, len) = self.get_type()?;
+            match tag {
+                Tag::SmallInteger => self.take(2).map(drop)?,
+                Tag::Integer      => self.take(5).map(drop)?,
+                Tag::SmallBig     => self.take(3 + len).map(drop)?,
+                Tag::LargeBig     => self.take(6 + len).map(drop)?,
+                Tag::Float        => self.take(9).map(drop)?,
+                Tag::Atom         => self.skip_atom()?,
+                Tag::Ref          => { self.take(3)?; self.skip_atom()?; self.take(4 + 4 * len)?; }
+                Tag::Pid          => { self.take(1)?; self.skip_atom()?; self.take(12)?; }
+                Tag::Port         => {
+                    let tag = self.take(1)?[0];
+                    self.skip_atom()?;
+                    self.take(if tag == ERL_V4_PORT_EXT { 12 } else { 8 })?;
+                }
+                Tag::SmallTuple   => { self.take(2)?; pending += len; }
+                Tag::LargeTuple   => { self.take(5)?; pending += len; }
+                Tag::Map          => { self.take(5)?; pending += 2 * len; }
+                Tag::Nil          => self.take(1).map(drop)?,
+                Tag::String       => self.take(3 + len).map(drop)?,
+                Tag::List         => { self.take(5)?; pending += len + 1; }
+                Tag::Binary       => self.take(5 + len).map(drop)?,
+            }
+        }
+        Ok(())
+    }
+
+    // the name is not checked, only stepped over
+    fn skip_atom(&mut self) -> Result<(), Error> {
+        match self.get_type()? {
+            (Tag::Atom, len) => match self.remaining()[0] {
+                ERL_SMALL_ATOM_EXT | ERL_SMALL_ATOM_UTF8_EXT => self.take(2 + len).map(drop),
+                _ => self.take(3 + len).map(drop),
+            },
+            (tag, _) => Err(invalid_data!("skip_atom: {:?}", tag)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use crate::term;
+    use crate::to_vec;
+    use crate::Encoder;
+
+    use super::{Decoder, Tag};
+
+    #[test]
+    fn get_type() {
+        for (input, expected) in vec![
+            (vec![0x61, 0x01],                               (Tag::SmallInteger, 0)),
+            (vec![0x62, 0x00, 0x00, 0x01, 0x00],             (Tag::Integer, 0)),
+            (vec![0x6e, 0x08, 0x00],                         (Tag::SmallBig, 8)),
+            (vec![0x76, 0x00, 0x02, 0x6f, 0x6b],             (Tag::Atom, 2)),
+            (vec![0x68, 0x03],                               (Tag::SmallTuple, 3)),
+            (vec![0x74, 0x00, 0x00, 0x00, 0x02],             (Tag::Map, 2)),
+            (vec![0x6a],                                     (Tag::Nil, 0)),
+            (vec![0x6b, 0x00, 0x03],                         (Tag::String, 3)),
+            (vec![0x6c, 0x00, 0x00, 0x00, 0x04],             (Tag::List, 4)),
+            (vec![0x6d, 0x00, 0x00, 0x00, 0x05],             (Tag::Binary, 5)),
+        ] {
+            let dec = Decoder::new(&input);
+            assert_eq!(dec.get_type().unwrap(), expected);
+            assert_eq!(dec.position(), 0);
+        }
+        assert!(Decoder::new(&[0x00]).get_type().is_err());
+        assert!(Decoder::new(&[]).get_type().is_err());
+    }
+
+    #[test]
+    fn decode_long() {
+        for input in vec![
+            i64::MIN,
+            i64::MIN + 1,
+            -1 << 27,
+            -1,
+            0,
+            255,
+            1 << 27,
+            i64::MAX,
+        ] {
+            let mut enc = Encoder::new(Vec::new());
+            enc.encode_long(input).unwrap();
+            let vec = enc.into_inner();
+            let mut dec = Decoder::new(&vec);
+            assert_eq!(dec.decode_long().unwrap(), input);
+            assert_eq!(dec.position(), vec.len());
+        }
+        let vec = to_vec(&u64::MAX).unwrap();
+        let mut dec = Decoder::new(&vec);
+        assert!(dec.decode_long().is_err());
+        assert_eq!(dec.position(), 0);
+        assert_eq!(dec.decode_ulong().unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn decode_atom() {
+        let input = vec![0x77, 0x02, 0x6f, 0x6b, 0x64, 0x00, 0x04, 0x74, 0x72, 0x75, 0x65];
+        let mut dec = Decoder::new(&input);
+        assert_eq!(dec.decode_atom().unwrap(), term::Atom::UTF8Small("ok".to_owned()));
+        assert_eq!(dec.position(), 4);
+        assert!(dec.decode_boolean().unwrap());
+        assert_eq!(dec.position(), input.len());
+        assert!(dec.decode_atom().is_err());
+    }
+
+    #[test]
+    fn decode_headers() {
+        // {[1], #{}, <<"ab">>, "cd", 1.5}
+        let input = vec![
+            0x68, 0x05,
+            0x6c, 0x00, 0x00, 0x00, 0x01, 0x61, 0x01, 0x6a,
+            0x74, 0x00, 0x00, 0x00, 0x00,
+            0x6d, 0x00, 0x00, 0x00, 0x02, 0x61, 0x62,
+            0x6b, 0x00, 0x02, 0x63, 0x64,
+            0x46, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut dec = Decoder::new(&input);
+        assert!(dec.decode_list_header().is_err());
+        assert_eq!(dec.position(), 0);
+        assert_eq!(dec.decode_tuple_header().unwrap(), 5);
+        assert_eq!(dec.decode_list_header().unwrap(), 1);
+        assert_eq!(dec.decode_long().unwrap(), 1);
+        assert_eq!(dec.decode_list_header().unwrap(), 0);
+        assert_eq!(dec.decode_map_header().unwrap(), 0);
+        assert_eq!(dec.decode_binary().unwrap(), b"ab");
+        assert_eq!(dec.decode_string().unwrap(), b"cd");
+        assert_eq!(dec.decode_double().unwrap(), 1.5);
+        assert_eq!(dec.position(), input.len());
+    }
+
+    #[test]
+    fn decode_pid() {
+        let input = term::Pid {
+            node: term::Atom::UTF8("n".to_owned()),
+            num: 1,
+            serial: 2,
+            creation: 3,
+        };
+        let vec = to_vec(&input).unwrap();
+        let mut dec = Decoder::new(&vec);
+        assert_eq!(dec.decode_pid().unwrap(), input);
+        assert_eq!(dec.position(), vec.len());
+
+        let mut dec = Decoder::new(&vec[..vec.len() - 1]);
+        assert!(dec.decode_pid().is_err());
+        assert_eq!(dec.position(), 0);
+    }
+
+    #[test]
+    fn skip_term() {
+        let input = (
+            vec![(1u8, "a".to_owned())],
+            std::collections::BTreeMap::from([(i64::MAX, (2.0f64, -1i32))]),
+            term::Pid {
+                node: term::Atom::UTF8("n".to_owned()),
+                num: 1,
+                serial: 2,
+                creation: 3,
+            },
+            term::Ref {
+                len: 2,
+                node: term::Atom::Latin1("m".to_owned()),
+                creation: 1,
+                n: Some(vec![1, 2]),
+            },
+        );
+        let mut vec = to_vec(&input).unwrap();
+        let len = vec.len();
+        vec.push(0x6a);
+        let mut dec = Decoder::new(&vec);
+        dec.skip_term().unwrap();
+        assert_eq!(dec.position(), len);
+        dec.skip_term().unwrap();
+        assert_eq!(dec.position(), vec.len());
+        assert!(dec.skip_term().is_err());
+
+        let mut dec = Decoder::new(&vec[..len - 1]);
+        assert!(dec.skip_term().is_err());
+        assert_eq!(dec.position(), 0);
+    }
+}
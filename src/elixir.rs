@@ -0,0 +1,203 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::{de, ser};
+
+use crate::term::{Atom, AtomStr};
+
+pub const ELIXIR_STRUCT: &str = "$ei::ElixirStruct";
+
+// %Module{...}, a map with __struct__ => :"Elixir.Module", named by the struct's serde name;
+//  decoded from such a map, __struct__ and unknown keys are ignored
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct ElixirStruct<T>(pub T);
+
+// [key: value, ...], a list of {atom, value}
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Keyword<V>(pub Vec<(String, V)>);
+
+impl<T> ser::Serialize for ElixirStruct<T>
+where
+    T: ser::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(ELIXIR_STRUCT, &self.0)
+    }
+}
+
+impl<V> ser::Serialize for Keyword<V>
+where
+    V: ser::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.collect_seq(self.0.iter().map(|(k, v)| (AtomStr(k), v)))
+    }
+}
+
+struct StructVisitor<T>(PhantomData<T>);
+
+impl<'de, T> de::Visitor<'de> for StructVisitor<T>
+where
+    T: de::Deserialize<'de>,
+{
+    type Value = ElixirStruct<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an elixir struct")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(ElixirStruct)
+    }
+}
+
+impl<'de, T> de::Deserialize<'de> for ElixirStruct<T>
+where
+    T: de::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(ELIXIR_STRUCT, StructVisitor(PhantomData))
+    }
+}
+
+impl<'de, V> de::Deserialize<'de> for Keyword<V>
+where
+    V: de::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let vec: Vec<(Atom, V)> = de::Deserialize::deserialize(deserializer)?;
+        Ok(Keyword(
+            vec.into_iter()
+                .map(|(k, v)| (k.as_str().to_owned(), v))
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+mod tests {
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_reader, to_vec, Deserializer, Serializer};
+
+    use super::{ElixirStruct, Keyword};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(rename = "MyApp.User")]
+    struct User {
+        name: String,
+        age: u8,
+        email: Option<String>,
+    }
+
+    fn encode<T: Serialize>(v: &T) -> Vec<u8> {
+        let mut vec = Vec::new();
+        v.serialize(&mut Serializer::new(&mut vec).elixir(true)).unwrap();
+        vec
+    }
+
+    fn decode<'de, T: Deserialize<'de>>(v: &[u8]) -> T {
+        T::deserialize(&mut Deserializer::new(v).elixir(true)).unwrap()
+    }
+
+    #[test]
+    fn elixir_struct() {
+        let user = User { name: "jo".to_string(), age: 7, email: None };
+        let expected = vec![
+            0x74, 0x00, 0x00, 0x00, 0x04,
+            0x77, 0x0a, 0x5f, 0x5f, 0x73, 0x74, 0x72, 0x75, 0x63, 0x74, 0x5f, 0x5f,
+            0x77, 0x11, 0x45, 0x6c, 0x69, 0x78, 0x69, 0x72, 0x2e,
+                        0x4d, 0x79, 0x41, 0x70, 0x70, 0x2e, 0x55, 0x73, 0x65, 0x72,
+            0x77, 0x04, 0x6e, 0x61, 0x6d, 0x65,
+            0x6d, 0x00, 0x00, 0x00, 0x02, 0x6a, 0x6f,
+            0x77, 0x03, 0x61, 0x67, 0x65,
+            0x61, 0x07,
+            0x77, 0x05, 0x65, 0x6d, 0x61, 0x69, 0x6c,
+            0x77, 0x03, 0x6e, 0x69, 0x6c,
+        ];
+        assert_eq!(expected, encode(&user));
+        assert_eq!(user, decode::<User>(&expected));
+    }
+
+    #[test]
+    fn elixir_struct_without_profile() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(rename = "Elixir.Point")]
+        struct Point { x: u8 }
+
+        let expected = vec![
+            0x74, 0x00, 0x00, 0x00, 0x02,
+            0x77, 0x0a, 0x5f, 0x5f, 0x73, 0x74, 0x72, 0x75, 0x63, 0x74, 0x5f, 0x5f,
+            0x77, 0x0c, 0x45, 0x6c, 0x69, 0x78, 0x69, 0x72, 0x2e, 0x50, 0x6f, 0x69, 0x6e, 0x74,
+            0x77, 0x01, 0x78,
+            0x61, 0x01,
+        ];
+        assert_eq!(expected, to_vec(&ElixirStruct(Point { x: 1 })).unwrap());
+        assert_eq!(ElixirStruct(Point { x: 1 }), from_reader(&expected[..]).unwrap());
+        assert_eq!(vec![0x68, 0x01, 0x61, 0x01], to_vec(&Point { x: 1 }).unwrap());
+        assert!(to_vec(&ElixirStruct(1u8)).is_err());
+    }
+
+    #[test]
+    fn elixir_struct_unknown_keys() {
+        let input = vec![
+            0x74, 0x00, 0x00, 0x00, 0x03,
+            0x77, 0x03, 0x61, 0x67, 0x65,
+            0x61, 0x07,
+            0x77, 0x05, 0x65, 0x78, 0x74, 0x72, 0x61,
+            0x68, 0x02, 0x61, 0x01, 0x6a,
+            0x77, 0x04, 0x6e, 0x61, 0x6d, 0x65,
+            0x6d, 0x00, 0x00, 0x00, 0x02, 0x6a, 0x6f,
+        ];
+        let expected = User { name: "jo".to_string(), age: 7, email: None };
+        assert_eq!(expected, decode::<User>(&input));
+    }
+
+    #[test]
+    fn elixir_option() {
+        assert_eq!(vec![0x77, 0x03, 0x6e, 0x69, 0x6c], encode(&None::<u8>));
+        assert_eq!(vec![0x61, 0x01], encode(&Some(1u8)));
+        assert_eq!(None, decode::<Option<u8>>(&[0x77, 0x03, 0x6e, 0x69, 0x6c]));
+        assert_eq!(Some(1), decode::<Option<u8>>(&[0x61, 0x01]));
+        assert_eq!(Some(true), decode::<Option<bool>>(&[0x77, 0x04, 0x74, 0x72, 0x75, 0x65]));
+    }
+
+    #[test]
+    fn elixir_string() {
+        let expected = vec![0x6d, 0x00, 0x00, 0x00, 0x02, 0xc3, 0xa9];
+        assert_eq!(expected, encode(&"é"));
+        assert_eq!("é", decode::<String>(&expected));
+        assert_eq!(vec![0x6b, 0x00, 0x01, 0xe9], to_vec(&"é").unwrap());
+    }
+
+    #[test]
+    fn keyword() {
+        let input = Keyword(vec![("a".to_string(), 1u8), ("b".to_string(), 2u8)]);
+        let expected = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x02,
+            0x68, 0x02, 0x77, 0x01, 0x61, 0x61, 0x01,
+            0x68, 0x02, 0x77, 0x01, 0x62, 0x61, 0x02,
+            0x6a,
+        ];
+        assert_eq!(expected, to_vec(&input).unwrap());
+        assert_eq!(input, from_reader(&expected[..]).unwrap());
+        assert_eq!(Keyword(vec![]), from_reader::<_, Keyword<u8>>(&[0x6a][..]).unwrap());
+    }
+}
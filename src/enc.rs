@@ -0,0 +1,417 @@
+use std::io;
+
+use crate::consts::*;
+use crate::error::Error;
+use crate::i27;
+use crate::io::Writer;
+use crate::term::{Atom, Pid, Ref};
+
+impl<W> Writer<W>
+where
+    W: io::Write,
+{
+    pub(crate) fn write_i27(&mut self, v: i32) -> Result<(), Error> {
+        self.write_u8(ERL_INTEGER_EXT)?;
+        self.write_i32(v)
+    }
+
+    pub(crate) fn write_small_big(&mut self, s: u8, v: u64) -> Result<(), Error> {
+        let vec = v.to_le_bytes();
+        let len = vec.iter().rposition(|&u| u != 0).map_or(0, |u| u + 1);
+        self.write_all(&[ERL_SMALL_BIG_EXT, len as u8, s])?;
+        self.write_all(&vec[..len])
+    }
+
+    pub(crate) fn write_big(&mut self, s: u8, v: &[u8]) -> Result<(), Error> {
+        let v = &v[..v.iter().rposition(|&u| u != 0).map_or(0, |u| u + 1)];
+        match v.len() {
+            u if u <= u8::MAX as usize => {
+                self.write_all(&[ERL_SMALL_BIG_EXT, u as u8, s])?;
+            }
+            u if u <= u32::MAX as usize => {
+                self.write_u8(ERL_LARGE_BIG_EXT)?;
+                self.write_u32(u as u32)?;
+                self.write_u8(s)?;
+            }
+            u => return Err(invalid_input!("write_big: {}", u)),
+        }
+        self.write_all(v)
+    }
+
+    pub(crate) fn write_tuple(&mut self, len: usize) -> Result<(), Error> {
+        if len > u8::MAX as usize {
+            self.write_u8(ERL_LARGE_TUPLE_EXT)?;
+            self.write_u32(len as u32)
+        } else {
+            self.write_all(&[ERL_SMALL_TUPLE_EXT, len as u8])
+        }
+    }
+
+    // one byte per char, refusing anything above U+00FF
+    pub(crate) fn write_latin1(&mut self, s: &str) -> Result<(), Error> {
+        match s.chars().find(|&c| c as u32 > u8::MAX as u32) {
+            None => {
+                let vec = s.chars().map(|c| c as u8).collect::<Vec<_>>();
+                self.write_all(&vec)
+            }
+            Some(c) => Err(invalid_input!("write_latin1: {:?}", c)),
+        }
+    }
+
+    // a list of code points, compacted to STRING_EXT when possible
+    pub(crate) fn write_charlist(&mut self, s: &str) -> Result<(), Error> {
+        let len = s.chars().count();
+        if len == 0 {
+            return self.write_u8(ERL_NIL_EXT);
+        }
+        if len <= u16::MAX as usize && s.chars().all(|c| (c as u32) <= u8::MAX as u32) {
+            self.write_u8(ERL_STRING_EXT)?;
+            self.write_u16(len as u16)?;
+            return self.write_latin1(s);
+        }
+        match len {
+            u if u <= u32::MAX as usize => {
+                self.write_u8(ERL_LIST_EXT)?;
+                self.write_u32(u as u32)?;
+            }
+            u => return Err(invalid_input!("write_charlist: {}", u)),
+        }
+        for c in s.chars() {
+            match c as u32 {
+                u if u <= u8::MAX as u32 => self.write_all(&[ERL_SMALL_INTEGER_EXT, u as u8])?,
+                u => self.write_i27(u as i32)?,
+            }
+        }
+        self.write_u8(ERL_NIL_EXT)
+    }
+
+    // atoms are limited to 255 characters whatever the encoding
+    #[rustfmt::skip]
+    pub(crate) fn write_atom_ext(&mut self, tag: u8, s: &str) -> Result<(), Error> {
+        match (tag, s.chars().count(), s.len()) {
+            (ERL_ATOM_EXT, u, _) if u < MAXATOMLEN => {
+                self.write_u8(ERL_ATOM_EXT)?;
+                self.write_u16(u as u16)?;
+                self.write_latin1(s)
+            }
+            (ERL_SMALL_ATOM_UTF8_EXT, u, v) if u < MAXATOMLEN && v <= u8::MAX as usize => {
+                self.write_u8(ERL_SMALL_ATOM_UTF8_EXT)?;
+                self.write_u8(v as u8)?;
+                self.write_all(s.as_bytes())
+            }
+            (ERL_ATOM_UTF8_EXT, u, v) if u < MAXATOMLEN && v < MAXATOMLEN_UTF8 => {
+                self.write_u8(ERL_ATOM_UTF8_EXT)?;
+                self.write_u16(v as u16)?;
+                self.write_all(s.as_bytes())
+            }
+            o => Err(invalid_input!("write_atom_ext: {:?}", o)),
+        }
+    }
+
+    // SMALL_ATOM_UTF8_EXT when the name fits
+    pub(crate) fn write_atom_utf8(&mut self, s: &str) -> Result<(), Error> {
+        if s.len() <= u8::MAX as usize {
+            self.write_atom_ext(ERL_SMALL_ATOM_UTF8_EXT, s)
+        } else {
+            self.write_atom_ext(ERL_ATOM_UTF8_EXT, s)
+        }
+    }
+
+    #[rustfmt::skip]
+    pub(crate) fn write_atom(&mut self, atom: &Atom) -> Result<(), Error> {
+        match atom {
+            Atom::Latin1(s)    => self.write_atom_ext(ERL_ATOM_EXT, s),
+            Atom::UTF8Small(s) => self.write_atom_ext(ERL_SMALL_ATOM_UTF8_EXT, s),
+            Atom::UTF8(s)      => self.write_atom_ext(ERL_ATOM_UTF8_EXT, s),
+        }
+    }
+}
+
+// -- ~/lib/erl_interface/src/encode/encode_*.c --
+
+pub struct Encoder<W> {
+    writer: Writer<W>,
+}
+
+impl<W> Encoder<W>
+where
+    W: io::Write,
+{
+    pub fn new(w: W) -> Self {
+        Encoder {
+            writer: Writer::new(w),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    pub fn encode_version(&mut self) -> Result<(), Error> {
+        self.writer.write_u8(ERL_VERSION_MAGIC)
+    }
+
+    pub fn encode_atom(&mut self, s: &str) -> Result<(), Error> {
+        self.writer.write_atom_utf8(s)
+    }
+
+    pub fn encode_boolean(&mut self, b: bool) -> Result<(), Error> {
+        self.encode_atom(if b { "true" } else { "false" })
+    }
+
+    #[rustfmt::skip]
+    pub fn encode_long(&mut self, v: i64) -> Result<(), Error> {
+        match v {
+            i if range!(i,  u8, i64) => self.writer.write_all(&[ERL_SMALL_INTEGER_EXT, i as u8]),
+            i if range!(i, i27, i64) => self.writer.write_i27(i as i32),
+            i if i >= 0              => self.writer.write_small_big(0, i as u64),
+            i                        => self.writer.write_small_big(1, i.unsigned_abs()),
+        }
+    }
+
+    #[rustfmt::skip]
+    pub fn encode_ulong(&mut self, v: u64) -> Result<(), Error> {
+        match v {
+            u if u <=  u8::MAX as u64 => self.writer.write_all(&[ERL_SMALL_INTEGER_EXT, u as u8]),
+            u if u <= i27::MAX as u64 => self.writer.write_i27(u as i32),
+            u                         => self.writer.write_small_big(0, u),
+        }
+    }
+
+    pub fn encode_double(&mut self, f: f64) -> Result<(), Error> {
+        self.writer.write_u8(NEW_FLOAT_EXT)?;
+        self.writer.write_f64(f)
+    }
+
+    pub fn encode_binary(&mut self, v: &[u8]) -> Result<(), Error> {
+        match v.len() {
+            u if u <= u32::MAX as usize => {
+                self.writer.write_u8(ERL_BINARY_EXT)?;
+                self.writer.write_u32(u as u32)?;
+                self.writer.write_all(v)
+            }
+            u => Err(invalid_input!("encode_binary: {}", u)),
+        }
+    }
+
+    // a list of code points, as STRING_EXT when every char is latin-1
+    pub fn encode_string(&mut self, s: &str) -> Result<(), Error> {
+        self.writer.write_charlist(s)
+    }
+
+    pub fn encode_tuple_header(&mut self, n: usize) -> Result<(), Error> {
+        self.writer.write_tuple(n)
+    }
+
+    // n > 0 must be followed by n elements and a tail, usually encode_empty_list
+    pub fn encode_list_header(&mut self, n: usize) -> Result<(), Error> {
+        match n {
+            0 => self.encode_empty_list(),
+            u if u <= u32::MAX as usize => {
+                self.writer.write_u8(ERL_LIST_EXT)?;
+                self.writer.write_u32(u as u32)
+            }
+            u => Err(invalid_input!("encode_list_header: {}", u)),
+        }
+    }
+
+    pub fn encode_map_header(&mut self, n: usize) -> Result<(), Error> {
+        match n {
+            u if u <= u32::MAX as usize => {
+                self.writer.write_u8(ERL_MAP_EXT)?;
+                self.writer.write_u32(u as u32)
+            }
+            u => Err(invalid_input!("encode_map_header: {}", u)),
+        }
+    }
+
+    pub fn encode_empty_list(&mut self) -> Result<(), Error> {
+        self.writer.write_u8(ERL_NIL_EXT)
+    }
+
+    pub fn encode_pid(&mut self, p: &Pid) -> Result<(), Error> {
+        self.writer.write_u8(ERL_NEW_PID_EXT)?;
+        self.writer.write_atom(&p.node)?;
+        self.writer.write_u32(p.num)?;
+        self.writer.write_u32(p.serial)?;
+        self.writer.write_u32(p.creation)
+    }
+
+    pub fn encode_ref(&mut self, r: &Ref) -> Result<(), Error> {
+        let n = r.n.as_deref().unwrap_or(&[]);
+        if r.len as usize != n.len() || !range!(r.len, 0, 5, i16) {
+            return Err(invalid_input!("encode_ref: {}", r.len));
+        }
+        self.writer.write_u8(ERL_NEWER_REFERENCE_EXT)?;
+        self.writer.write_i16(r.len)?;
+        self.writer.write_atom(&r.node)?;
+        self.writer.write_u32(r.creation)?;
+        n.iter().try_for_each(|&u| self.writer.write_u32(u))
+    }
+
+    // v: magnitude, little-endian
+    pub fn encode_big(&mut self, neg: bool, v: &[u8]) -> Result<(), Error> {
+        self.writer.write_big(neg as u8, v)
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use crate::i27;
+    use crate::term;
+    use crate::to_vec;
+
+    use super::Encoder;
+
+    macro_rules! test {
+        ($f: expr) => {{
+            let mut enc = Encoder::new(Vec::new());
+            $f(&mut enc).unwrap();
+            enc.into_inner()
+        }};
+    }
+
+    #[test]
+    fn encode_long() {
+        for input in vec![
+            i64::MIN + 1,
+            i27::MIN as i64 - 1,
+            i27::MIN as i64,
+            -1,
+            0,
+            u8::MAX as i64,
+            u8::MAX as i64 + 1,
+            i27::MAX as i64,
+            i27::MAX as i64 + 1,
+            i64::MAX,
+        ] {
+            assert_eq!(to_vec(&input).unwrap(), test!(|e: &mut Encoder<_>| e.encode_long(input)), "{:?}", input);
+        }
+        assert_eq!(
+            vec![0x6e, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80],
+            test!(|e: &mut Encoder<_>| e.encode_long(i64::MIN))
+        );
+    }
+
+    #[test]
+    fn encode_ulong() {
+        for input in vec![0, u8::MAX as u64, i27::MAX as u64, i27::MAX as u64 + 1, u64::MAX] {
+            assert_eq!(to_vec(&input).unwrap(), test!(|e: &mut Encoder<_>| e.encode_ulong(input)), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn encode_double() {
+        assert_eq!(to_vec(&1.5).unwrap(), test!(|e: &mut Encoder<_>| e.encode_double(1.5)));
+    }
+
+    #[test]
+    fn encode_atom() {
+        assert_eq!(to_vec(&term::Atom::from("ok")).unwrap(), test!(|e: &mut Encoder<_>| e.encode_atom("ok")));
+        assert_eq!(to_vec(&true).unwrap(), test!(|e: &mut Encoder<_>| e.encode_boolean(true)));
+
+        let s = "é".repeat(200);
+        assert_eq!(to_vec(&term::Atom::UTF8(s.clone())).unwrap(), test!(|e: &mut Encoder<_>| e.encode_atom(&s)));
+        assert!(Encoder::new(Vec::new()).encode_atom(&"a".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn encode_string() {
+        for input in vec!["", "hello", "café", "ア"] {
+            assert_eq!(to_vec(&input).unwrap(), test!(|e: &mut Encoder<_>| e.encode_string(input)), "{:?}", input);
+        }
+        assert_eq!(vec![0x6b, 0x00, 0x01, 0xe9], test!(|e: &mut Encoder<_>| e.encode_string("é")));
+        let s = "a".repeat(u16::MAX as usize + 1);
+        let v = test!(|e: &mut Encoder<_>| e.encode_string(&s));
+        assert_eq!(vec![0x6c, 0x00, 0x01, 0x00, 0x00, 0x61, 0x61], v[..7].to_vec());
+        assert_eq!(Some(&0x6a), v.last());
+    }
+
+    #[test]
+    fn encode_binary() {
+        assert_eq!(
+            vec![0x6d, 0x00, 0x00, 0x00, 0x02, 0x01, 0x02],
+            test!(|e: &mut Encoder<_>| e.encode_binary(&[1, 2]))
+        );
+    }
+
+    #[test]
+    fn encode_tuple_header() {
+        assert_eq!(
+            to_vec(&(1u8, term::Atom::from("a"))).unwrap(),
+            test!(|e: &mut Encoder<_>| {
+                e.encode_tuple_header(2)?;
+                e.encode_long(1)?;
+                e.encode_atom("a")
+            })
+        );
+        assert_eq!(vec![0x69, 0x00, 0x00, 0x01, 0x00], test!(|e: &mut Encoder<_>| e.encode_tuple_header(256)));
+    }
+
+    #[test]
+    fn encode_list_header() {
+        assert_eq!(
+            to_vec(&vec![1u8, 2]).unwrap(),
+            test!(|e: &mut Encoder<_>| {
+                e.encode_list_header(2)?;
+                e.encode_long(1)?;
+                e.encode_long(2)?;
+                e.encode_empty_list()
+            })
+        );
+        assert_eq!(to_vec(&Vec::<u8>::new()).unwrap(), test!(|e: &mut Encoder<_>| e.encode_list_header(0)));
+    }
+
+    #[test]
+    fn encode_map_header() {
+        assert_eq!(
+            vec![0x74, 0x00, 0x00, 0x00, 0x01, 0x61, 0x01, 0x61, 0x02],
+            test!(|e: &mut Encoder<_>| {
+                e.encode_map_header(1)?;
+                e.encode_long(1)?;
+                e.encode_long(2)
+            })
+        );
+    }
+
+    #[test]
+    fn encode_pid() {
+        let p = term::Pid {
+            node: term::Atom::UTF8("n".to_owned()),
+            num: 1,
+            serial: 2,
+            creation: 3,
+        };
+        assert_eq!(to_vec(&p).unwrap(), test!(|e: &mut Encoder<_>| e.encode_pid(&p)));
+    }
+
+    #[test]
+    fn encode_ref() {
+        for input in vec![
+            term::Ref { len: 0, node: term::Atom::UTF8("n".to_owned()), creation: 1, n: None },
+            term::Ref { len: 3, node: term::Atom::UTF8("n".to_owned()), creation: 1, n: Some(vec![2, 3, 4]) },
+        ] {
+            assert_eq!(to_vec(&input).unwrap(), test!(|e: &mut Encoder<_>| e.encode_ref(&input)), "{:?}", input);
+        }
+        let r = term::Ref { len: 2, node: term::Atom::UTF8("n".to_owned()), creation: 1, n: None };
+        assert!(Encoder::new(Vec::new()).encode_ref(&r).is_err());
+    }
+
+    #[test]
+    fn encode_big() {
+        assert_eq!(to_vec(&u64::MAX).unwrap(), test!(|e: &mut Encoder<_>| e.encode_big(false, &[0xff; 8])));
+        assert_eq!(
+            vec![0x6e, 0x09, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01],
+            test!(|e: &mut Encoder<_>| e.encode_big(true, &[0, 0, 0, 0, 0, 0, 0, 0, 1, 0]))
+        );
+        let v = test!(|e: &mut Encoder<_>| e.encode_big(false, &[1; 256]));
+        assert_eq!(vec![0x6f, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01], v[..7].to_vec());
+    }
+}
@@ -4,8 +4,6 @@ use std::io;
 use std::str;
 use std::string;
 
-use serde;
-
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
@@ -22,19 +20,25 @@ impl fmt::Display for Error {
             Error::Io(ref e) => e.fmt(f),
             Error::Str(ref e) => e.fmt(f),
             Error::String(ref e) => e.fmt(f),
-            Error::Custom(_) => unimplemented!("fmt"),
+            Error::Custom(ref s) => f.write_str(s),
         }
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
 impl serde::de::Error for Error {
-    fn custom<T: fmt::Display>(_msg: T) -> Self {
-        unimplemented!("custom")
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
     }
 }
 
 impl serde::ser::Error for Error {
-    fn custom<T: fmt::Display>(_msg: T) -> Self {
-        unimplemented!("custom")
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
     }
 }
@@ -0,0 +1,386 @@
+use serde::ser;
+
+use crate::consts::*;
+use crate::error::Error;
+use crate::ser::to_vec;
+use crate::value::{self, Value};
+
+// -- ~/erts/emulator/beam/utils.c --
+
+const HCONST: u32 = 0x9e37_79b9;
+
+const fn hconst(n: u32) -> u32 {
+    HCONST.wrapping_mul(n)
+}
+
+#[rustfmt::skip]
+fn mix(mut a: u32, mut b: u32, mut c: u32) -> (u32, u32, u32) {
+    a = a.wrapping_sub(b).wrapping_sub(c); a ^= c >> 13;
+    b = b.wrapping_sub(c).wrapping_sub(a); b ^= a << 8;
+    c = c.wrapping_sub(a).wrapping_sub(b); c ^= b >> 13;
+    a = a.wrapping_sub(b).wrapping_sub(c); a ^= c >> 12;
+    b = b.wrapping_sub(c).wrapping_sub(a); b ^= a << 16;
+    c = c.wrapping_sub(a).wrapping_sub(b); c ^= b >> 5;
+    a = a.wrapping_sub(b).wrapping_sub(c); a ^= c >> 3;
+    b = b.wrapping_sub(c).wrapping_sub(a); b ^= a << 10;
+    c = c.wrapping_sub(a).wrapping_sub(b); c ^= b >> 15;
+    (a, b, c)
+}
+
+fn block_hash(k: &[u8], initval: u32) -> u32 {
+    let word = |v: &[u8]| {
+        v.iter()
+            .enumerate()
+            .fold(0u32, |w, (i, &u)| w.wrapping_add((u as u32) << (i * 8)))
+    };
+    let (mut a, mut b, mut c) = (HCONST, HCONST, initval);
+    let mut chunks = k.chunks_exact(12);
+    for v in &mut chunks {
+        a = a.wrapping_add(word(&v[0..4]));
+        b = b.wrapping_add(word(&v[4..8]));
+        c = c.wrapping_add(word(&v[8..12]));
+        let t = mix(a, b, c);
+        a = t.0;
+        b = t.1;
+        c = t.2;
+    }
+    let v = chunks.remainder();
+    c = c.wrapping_add(k.len() as u32);
+    a = a.wrapping_add(word(&v[..v.len().min(4)]));
+    if v.len() > 4 {
+        b = b.wrapping_add(word(&v[4..v.len().min(8)]));
+    }
+    if v.len() > 8 {
+        c = c.wrapping_add(word(&v[8..]) << 8); // the first byte of c is reserved for the length
+    }
+    mix(a, b, c).2
+}
+
+// -- ~/erts/emulator/beam/atom.c --
+
+fn atom_hash(s: &str) -> u32 {
+    let p = s.as_bytes();
+    let mut h = 0u32;
+    let mut i = 0;
+    while i < p.len() {
+        let mut v = p[i];
+        i += 1;
+        // latin1 clutch for r16
+        if i < p.len() && (v & 0xfe) == 0xc2 && (p[i] & 0xc0) == 0x80 {
+            v = (v << 6) | (p[i] & 0x3f);
+            i += 1;
+        }
+        h = (h << 4).wrapping_add(v as u32);
+        let g = h & 0xf000_0000;
+        if g != 0 {
+            h ^= g >> 24;
+            h ^= g;
+        }
+    }
+    h
+}
+
+struct Hasher {
+    hash: u32,
+}
+
+impl Hasher {
+    fn uint32_2(&mut self, e1: u32, e2: u32, k: u32) {
+        self.hash = mix(k.wrapping_add(e1), k.wrapping_add(e2), self.hash).2;
+    }
+
+    fn uint32(&mut self, e: u32, k: u32) {
+        self.uint32_2(e, 0, k)
+    }
+
+    fn sint32(&mut self, y: i32, k: u32) {
+        if y < 0 {
+            self.uint32(y.wrapping_neg() as u32, k); // negative numbers are unnecessarily mixed twice
+        }
+        self.uint32(y as u32, k)
+    }
+
+    fn integer(&mut self, neg: bool, v: &[u8]) {
+        if v.len() <= 4 {
+            let u = v.iter().rev().fold(0u32, |u, &b| (u << 8) | b as u32);
+            if u < 1 << 27 || (neg && u == 1 << 27) {
+                let i = u as i32;
+                return self.sint32(if neg { -i } else { i }, HCONST);
+            }
+        }
+        let k = if neg { hconst(10) } else { hconst(11) };
+        for d in v.chunks(8) {
+            let mut b = [0u8; 8];
+            b[..d.len()].copy_from_slice(d);
+            let t = u64::from_le_bytes(b);
+            self.uint32_2(t as u32, (t >> 32) as u32, k);
+        }
+    }
+
+    fn list(&mut self, v: &[Value], tail: &Value) {
+        let mut i = 0;
+        loop {
+            let (mut c, mut sh) = (0, 0u32);
+            while let Some(Value::Integer(false, u)) = v.get(i) {
+                if u.len() > 1 {
+                    break;
+                }
+                // optimization for strings
+                sh = (sh << 8).wrapping_add(u.first().copied().unwrap_or(0) as u32);
+                if c == 3 {
+                    self.uint32(sh, hconst(4));
+                    c = 0;
+                    sh = 0;
+                } else {
+                    c += 1;
+                }
+                i += 1;
+            }
+            if c > 0 {
+                self.uint32(sh, hconst(4));
+            }
+            match v.get(i) {
+                Some(e) => {
+                    self.term(e);
+                    i += 1;
+                    if i == v.len() {
+                        return self.term(tail);
+                    }
+                }
+                None => return self.term(tail),
+            }
+        }
+    }
+
+    fn term(&mut self, v: &Value) {
+        match v {
+            Value::Integer(neg, v) => self.integer(*neg, v),
+            Value::Float(f) => {
+                let u = if *f == 0.0 { 0 } else { f.to_bits() }; // ensure positive 0.0
+                self.uint32_2((u >> 32) as u32, u as u32, hconst(12))
+            }
+            Value::Atom(s) => {
+                if self.hash == 0 {
+                    self.hash = atom_hash(s)
+                } else {
+                    self.uint32(atom_hash(s), hconst(3))
+                }
+            }
+            Value::Ref(_, _, n) => self.uint32(n.first().copied().unwrap_or(0), hconst(7)),
+            Value::Port(_, id, _) => self.uint32_2(*id as u32, (*id >> 32) as u32, hconst(6)),
+            Value::Pid(_, num, _, _) => self.uint32(*num, hconst(5)), // only the number is hashed
+            Value::Tuple(v) => {
+                self.uint32(v.len() as u32, hconst(9));
+                v.iter().for_each(|e| self.term(e))
+            }
+            Value::Map(m) => {
+                self.uint32(m.len() as u32, hconst(16));
+                if !m.is_empty() {
+                    // order independent: hash every pair on its own and xor them together
+                    let hash = self.hash;
+                    let mut xor = 0;
+                    for (k, v) in m {
+                        self.hash = 0;
+                        self.term(k);
+                        self.term(v);
+                        xor ^= self.hash;
+                    }
+                    self.hash = hash;
+                    self.uint32(xor, hconst(19));
+                }
+            }
+            Value::Nil => {
+                if self.hash == 0 {
+                    self.hash = 3_468_870_702
+                } else {
+                    self.uint32(2, hconst(2))
+                }
+            }
+            Value::List(v, t) => self.list(v, t),
+            Value::String(b) => {
+                // as list does a run of small integers, four to a word
+                for v in b.chunks(4) {
+                    let sh = v
+                        .iter()
+                        .fold(0u32, |sh, &u| (sh << 8).wrapping_add(u as u32));
+                    self.uint32(sh, hconst(4));
+                }
+                self.term(&Value::Nil)
+            }
+            Value::Binary(b) => {
+                let k = hconst(13).wrapping_add(self.hash);
+                self.hash = if b.is_empty() { k } else { block_hash(b, k) }
+            }
+        }
+    }
+}
+
+fn make_hash2(buf: &[u8]) -> Result<u32, Error> {
+    let buf = match buf.split_first() {
+        Some((&ERL_VERSION_MAGIC, v)) => v,
+        _ => buf,
+    };
+    let mut h = Hasher { hash: 0 };
+    value::parse(buf).map(|(v, _)| {
+        h.term(&v);
+        h.hash
+    })
+}
+
+// erlang:phash2/2 takes 1..=2^32, the whole 32-bit hash at 2^32
+fn range(hash: u32, range: u64) -> Result<u32, Error> {
+    match range {
+        u if u == 0 || u > 1 << 32 => Err(invalid_input!("phash2: range {}", range)),
+        u => Ok((hash as u64 % u) as u32),
+    }
+}
+
+pub fn phash2<T>(value: &T) -> Result<u32, Error>
+where
+    T: ser::Serialize + ?Sized,
+{
+    to_vec(value).and_then(|v| phash2_slice(&v))
+}
+
+pub fn phash2_range<T>(value: &T, r: u64) -> Result<u32, Error>
+where
+    T: ser::Serialize + ?Sized,
+{
+    to_vec(value).and_then(|v| phash2_slice_range(&v, r))
+}
+
+// raw ETF, with or without the leading ERL_VERSION_MAGIC
+pub fn phash2_slice(buf: &[u8]) -> Result<u32, Error> {
+    make_hash2(buf).map(|h| h & ((1 << 27) - 1))
+}
+
+pub fn phash2_slice_range(buf: &[u8], r: u64) -> Result<u32, Error> {
+    make_hash2(buf).and_then(|h| range(h, r))
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use std::collections::HashMap;
+
+    use crate::term;
+
+    use super::*;
+
+    // Only 'a' (hashpjw, returned as is for a lone atom) and [] (the constant make_hash2
+    //  starts from) follow from the OTP source. The other literal hashes in phash2_atom and
+    //  phash2_integer come from this implementation, not from an OTP node, and pin the current
+    //  behaviour only: replace them with erlang:phash2/1 output, citing its OTP release here.
+    //  The remaining cases compare terms OTP hashes alike, or apart, and stand as is
+    #[test]
+    fn make_hash2_nil() {
+        let mut h = Hasher { hash: 0 };
+        h.uint32(2, hconst(2));
+        assert_eq!(3_468_870_702, h.hash);
+        assert_eq!(3_468_870_702 & ((1 << 27) - 1), phash2_slice(&[0x83, 0x6a]).unwrap());
+    }
+
+    #[test]
+    fn phash2_atom() {
+        for (input, expected) in vec![
+            (vec![0x77, 0x01, 0x61],                               97),
+            (vec![0x64, 0x00, 0x02, 0x6f, 0x6b],                   1883),
+            (vec![0x64, 0x00, 0x04, 0x63, 0x61, 0x66, 0xe9],       432_201),
+            (vec![0x76, 0x00, 0x05, 0x63, 0x61, 0x66, 0xc3, 0xa9], 432_201),
+        ] {
+            assert_eq!(expected, phash2_slice(&input).unwrap(), "{:?}", input);
+        }
+        assert_eq!(1883, phash2(&term::Atom::from("ok")).unwrap());
+    }
+
+    #[test]
+    fn phash2_integer() {
+        // INTEGER_EXT, SMALL_BIG_EXT and LARGE_BIG_EXT of the same value hash alike
+        for (a, b) in vec![
+            (vec![0x62, 0x00, 0x00, 0x03, 0xe8], vec![0x6e, 0x02, 0x00, 0xe8, 0x03]),
+            (vec![0x62, 0xf8, 0x00, 0x00, 0x00], vec![0x6e, 0x04, 0x01, 0x00, 0x00, 0x00, 0x08]),
+            (vec![0x62, 0x08, 0x00, 0x00, 0x00], vec![0x6f, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x08]),
+        ] {
+            assert_eq!(phash2_slice(&a).unwrap(), phash2_slice(&b).unwrap(), "{:?}", a);
+        }
+        for (input, expected) in vec![
+            (0i64,             88_723_725),
+            (1,                 2_614_250),
+            (-1,               44_071_773),
+            (1 << 27,          12_354_923),
+            (i64::MAX,         28_179_613),
+        ] {
+            assert_eq!(expected, phash2(&input).unwrap(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn phash2_list() {
+        // STRING_EXT is a list of small integers
+        let a = vec![0x6b, 0x00, 0x03, 0x61, 0x62, 0x63];
+        let b = vec![0x6c, 0x00, 0x00, 0x00, 0x03, 0x61, 0x61, 0x61, 0x62, 0x61, 0x63, 0x6a];
+        assert_eq!(phash2_slice(&a).unwrap(), phash2_slice(&b).unwrap());
+        assert_eq!(phash2(&"abc").unwrap(), phash2(&vec![0x61u8, 0x62, 0x63]).unwrap());
+        assert_ne!(phash2(&"abc").unwrap(), phash2(&"acb").unwrap());
+    }
+
+    #[test]
+    fn phash2_map() {
+        let a = vec![0x74, 0x00, 0x00, 0x00, 0x02, 0x61, 0x01, 0x61, 0x02, 0x61, 0x03, 0x61, 0x04];
+        let b = vec![0x74, 0x00, 0x00, 0x00, 0x02, 0x61, 0x03, 0x61, 0x04, 0x61, 0x01, 0x61, 0x02];
+        assert_eq!(phash2_slice(&a).unwrap(), phash2_slice(&b).unwrap());
+
+        let map = [(1u8, 2u8), (3, 4)].iter().cloned().collect::<HashMap<_, _>>();
+        assert_eq!(phash2_slice(&a).unwrap(), phash2(&map).unwrap());
+    }
+
+    #[test]
+    fn phash2_binary() {
+        for (input, expected) in vec![
+            (vec![0x6d, 0x00, 0x00, 0x00, 0x00], hconst(13) & ((1 << 27) - 1)),
+        ] {
+            assert_eq!(expected, phash2_slice(&input).unwrap(), "{:?}", input);
+        }
+        let mut vec = vec![0x6d, 0x00, 0x00, 0x00, 0x19];
+        vec.extend(0..0x19);
+        assert!(phash2_slice(&vec).is_ok());
+        // every length of tail past the 12-byte blocks reaches the hash
+        let hashes = (12..=16).map(|n| {
+            let mut vec = vec![0x6d, 0x00, 0x00, 0x00, n as u8];
+            vec.resize(5 + n, 0);
+            phash2_slice(&vec).unwrap()
+        }).collect::<std::collections::HashSet<_>>();
+        assert_eq!(5, hashes.len());
+    }
+
+    #[test]
+    fn phash2_float() {
+        // NEW_FLOAT_EXT 0.0 and -0.0 hash alike
+        let a = vec![0x46, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let b = vec![0x46, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(phash2_slice(&a).unwrap(), phash2_slice(&b).unwrap());
+        assert_ne!(phash2(&1.0).unwrap(), phash2(&2.0).unwrap());
+    }
+
+    #[test]
+    fn phash2_nested() {
+        // {[1], a} and {[a], 1} mix the same items in a different shape
+        let a = vec![0x68, 0x02, 0x6c, 0x00, 0x00, 0x00, 0x01, 0x61, 0x01, 0x6a, 0x77, 0x01, 0x61];
+        let b = vec![0x68, 0x02, 0x6c, 0x00, 0x00, 0x00, 0x01, 0x77, 0x01, 0x61, 0x6a, 0x61, 0x01];
+        assert_ne!(phash2_slice(&a).unwrap(), phash2_slice(&b).unwrap());
+        assert_eq!(phash2_slice(&a).unwrap(), phash2(&(vec![1u8], term::Atom::from("a"))).unwrap());
+    }
+
+    #[test]
+    fn phash2_range() {
+        let v = (1u8, term::Atom::from("ok"));
+        let h = make_hash2(&crate::to_vec(&v).unwrap()).unwrap();
+        assert_eq!(h % 7, super::phash2_range(&v, 7).unwrap());
+        assert_eq!(0, super::phash2_range(&v, 1).unwrap());
+        assert_eq!(h, super::phash2_range(&v, 1 << 32).unwrap());
+        assert!(super::phash2_range(&v, 0).is_err());
+        assert!(super::phash2_range(&v, (1 << 32) + 1).is_err());
+    }
+}
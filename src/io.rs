@@ -2,12 +2,17 @@ use std::io;
 
 use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
 
+use crate::consts::*;
 use crate::error::Error;
 
 fn into(vec: Vec<u8>) -> Result<String, Error> {
     String::from_utf8(vec).map_err(Error::String)
 }
 
+fn latin1(vec: Vec<u8>) -> String {
+    vec.iter().map(|&u| u as char).collect()
+}
+
 #[derive(Debug)]
 pub enum Number {
     U8(u8),
@@ -31,6 +36,125 @@ where
         Reader { r }
     }
 
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.r
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.r
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.r
+    }
+
+    pub fn read_number(&mut self) -> Result<Number, Error> {
+        match self.read_u8()? {
+            ERL_SMALL_INTEGER_EXT => self.read_u8().map(Number::U8),
+            ERL_INTEGER_EXT => self.read_i32().map(Number::I32),
+            ERL_SMALL_BIG_EXT => self.read_small_big(),
+            u => Err(invalid_data!("read_number: {}", u)),
+        }
+    }
+
+    fn read_small_big(&mut self) -> Result<Number, Error> {
+        match self.read_u8()? {
+            a if a <= 8 => {
+                let s = self.read_u8()?;
+                let mut n = 0u64;
+                for i in 0..a {
+                    let u = self.read_u8()?;
+                    n |= (u as u64) << (i * 8);
+                }
+                Ok(Number::SmallBig(n, s))
+            }
+            a => Err(invalid_data!("read_small_big: {}", a)),
+        }
+    }
+
+    // copies exactly one encoded term into vec
+    pub fn read_term(&mut self, vec: &mut Vec<u8>) -> Result<(), Error> {
+        self.read_u8().and_then(|u| self.read_term_after(u, vec))
+    }
+
+    // as read_term, with the tag already consumed
+    #[rustfmt::skip]
+    pub fn read_term_after(&mut self, tag: u8, vec: &mut Vec<u8>) -> Result<(), Error> {
+        let mut tag = tag;
+        let mut pending = 1usize;
+        loop {
+            pending -= 1;
+            vec.push(tag);
+            match tag {
+                ERL_SMALL_INTEGER_EXT   => self.copy(vec, 1)?,
+                ERL_INTEGER_EXT         => self.copy(vec, 4)?,
+                ERL_SMALL_BIG_EXT       => { let n = self.copy_u8(vec)? as usize; self.copy(vec, 1 + n)? }
+                ERL_LARGE_BIG_EXT       => { let n = self.copy_u32(vec)? as usize; self.copy(vec, 1 + n)? }
+                NEW_FLOAT_EXT           => self.copy(vec, 8)?,
+                ERL_ATOM_EXT            |
+                ERL_SMALL_ATOM_EXT      |
+                ERL_ATOM_UTF8_EXT       |
+                ERL_SMALL_ATOM_UTF8_EXT => self.copy_atom(vec, tag)?,
+                ERL_NEWER_REFERENCE_EXT => {
+                    let n = self.copy_u16(vec)? as usize;
+                    let tag = self.copy_u8(vec)?;
+                    self.copy_atom(vec, tag)?;
+                    self.copy(vec, 4 + 4 * n)?
+                }
+                ERL_NEW_PORT_EXT | ERL_V4_PORT_EXT | ERL_NEW_PID_EXT => {
+                    let u = self.copy_u8(vec)?;
+                    self.copy_atom(vec, u)?;
+                    self.copy(vec, if tag == ERL_NEW_PORT_EXT { 8 } else { 12 })?
+                }
+                ERL_SMALL_TUPLE_EXT     => pending += self.copy_u8(vec)? as usize,
+                ERL_LARGE_TUPLE_EXT     => pending += self.copy_u32(vec)? as usize,
+                ERL_MAP_EXT             => pending += 2 * self.copy_u32(vec)? as usize,
+                ERL_NIL_EXT             => (),
+                ERL_STRING_EXT          => { let n = self.copy_u16(vec)? as usize; self.copy(vec, n)? }
+                ERL_LIST_EXT            => pending += self.copy_u32(vec)? as usize + 1,
+                ERL_BINARY_EXT          => { let n = self.copy_u32(vec)? as usize; self.copy(vec, n)? }
+                u                       => return Err(invalid_data!("read_term: {}", u)),
+            }
+            if pending == 0 {
+                return Ok(());
+            }
+            tag = self.read_u8()?;
+        }
+    }
+
+    #[rustfmt::skip]
+    fn copy_atom(&mut self, vec: &mut Vec<u8>, tag: u8) -> Result<(), Error> {
+        let n = match tag {
+            ERL_ATOM_EXT | ERL_ATOM_UTF8_EXT             => self.copy_u16(vec)? as usize,
+            ERL_SMALL_ATOM_EXT | ERL_SMALL_ATOM_UTF8_EXT => self.copy_u8(vec)? as usize,
+            u                                            => return Err(invalid_data!("copy_atom: {}", u)),
+        };
+        self.copy(vec, n)
+    }
+
+    fn copy(&mut self, vec: &mut Vec<u8>, len: usize) -> Result<(), Error> {
+        let from = vec.len();
+        vec.resize(from + len, 0);
+        self.read_exact(&mut vec[from..])
+    }
+
+    fn copy_u8(&mut self, vec: &mut Vec<u8>) -> Result<u8, Error> {
+        self.read_u8().inspect(|&u| vec.push(u))
+    }
+
+    fn copy_u16(&mut self, vec: &mut Vec<u8>) -> Result<u16, Error> {
+        self.read_u16()
+            .inspect(|u| vec.extend_from_slice(&u.to_be_bytes()))
+    }
+
+    fn copy_u32(&mut self, vec: &mut Vec<u8>) -> Result<u32, Error> {
+        self.read_u32()
+            .inspect(|u| vec.extend_from_slice(&u.to_be_bytes()))
+    }
+
     #[inline]
     pub fn read_exact_u8(&mut self) -> Result<Vec<u8>, Error> {
         self.read_u8()
@@ -52,21 +176,49 @@ where
     #[inline]
     pub fn read_exact_usize(&mut self, len: usize) -> Result<Vec<u8>, Error> {
         let mut vec = vec![0u8; len];
-        self.read_exact(&mut vec).and_then(|()| Ok(vec))
+        self.read_exact(&mut vec).map(|()| vec)
     }
 
     #[inline]
     pub fn read_string_u8(&mut self) -> Result<String, Error> {
-        self.read_exact_u8().and_then(|v| into(v))
+        self.read_exact_u8().and_then(into)
     }
 
     #[inline]
     pub fn read_string_u16(&mut self) -> Result<String, Error> {
-        self.read_exact_u16().and_then(|v| into(v))
+        self.read_exact_u16().and_then(into)
+    }
+
+    #[inline]
+    pub fn read_string_u32(&mut self) -> Result<String, Error> {
+        self.read_exact_u32().and_then(into)
+    }
+
+    #[inline]
+    pub fn read_latin1_u8(&mut self) -> Result<String, Error> {
+        self.read_exact_u8().map(latin1)
+    }
+
+    #[inline]
+    pub fn read_latin1_u16(&mut self) -> Result<String, Error> {
+        self.read_exact_u16().map(latin1)
     }
 
     // #region std::io
 
+    // None at a clean end of stream
+    pub fn read_opt_u8(&mut self) -> Result<Option<u8>, Error> {
+        let mut buf = [0u8; 1];
+        loop {
+            match self.r.read(&mut buf) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(buf[0])),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+    }
+
     #[inline]
     pub fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Error> {
         self.r.read_exact(buf).map_err(Error::Io)
@@ -130,6 +282,16 @@ where
         Writer { w }
     }
 
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.w
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
     // #region std::io
 
     #[inline]
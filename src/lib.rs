@@ -1,8 +1,13 @@
+// lets the code generated by ei-macros name this crate from inside it too
+extern crate self as ei;
+
 #[macro_use]
 mod macros;
 
 mod consts;
 mod i27;
+mod order;
+mod value;
 
 mod error;
 pub use crate::error::Error;
@@ -14,9 +19,30 @@ mod io;
 pub use crate::io::{Reader, Writer};
 
 mod de;
-pub use crate::de::{from_reader, Deserializer};
+pub use crate::de::{from_reader, Deserializer, ListIter, StreamDeserializer};
+
+mod atom;
+pub use crate::atom::{AtomTable, InternedAtom};
+mod binary;
+pub use crate::binary::{Binary, Charlist, IoData, IoList};
+mod proplist;
+pub use crate::proplist::Proplist;
+mod raw;
+pub use crate::raw::RawTerm;
+mod dec;
+pub use crate::dec::{Decoder, Tag};
+mod elixir;
+pub use crate::elixir::{ElixirStruct, Keyword};
+mod enc;
+pub use crate::enc::Encoder;
 
 mod ser;
-pub use crate::ser::{to_vec, Serializer};
+pub use crate::ser::{
+    encoded_size, to_vec, to_vec_deterministic, to_vec_with_capacity, Serializer,
+};
+
+mod hash;
+pub use crate::hash::{phash2, phash2_range, phash2_slice, phash2_slice_range};
 
 pub mod port;
+pub use ei_macros::port_export;
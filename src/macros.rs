@@ -15,10 +15,17 @@ macro_rules! deserialize {
 #[macro_export]
 macro_rules! serialize {
     ($v: expr) => {
-        $crate::to_vec($v, 256)
+        $crate::to_vec($v)
     };
     ($v: expr, $u: expr) => {
-        $crate::to_vec($v, $u)
+        $crate::to_vec_with_capacity($v, $u)
+    };
+}
+
+#[macro_export]
+macro_rules! port_exports {
+    ($($($p: ident)::+),* $(,)?) => {
+        $crate::port::Exports::new(vec![$($($p)::+::EXPORT),*])
     };
 }
 
@@ -0,0 +1,210 @@
+use std::cmp::Ordering;
+
+use crate::value::Value;
+
+const LIST: u8 = 9;
+
+static NIL: Value = Value::Nil;
+
+// number < atom < reference < fun < port < pid < tuple < map < nil < list < bit string
+fn rank(v: &Value) -> u8 {
+    match v {
+        Value::Integer(..) | Value::Float(_) => 0,
+        Value::Atom(_) => 1,
+        Value::Ref(..) => 2,
+        Value::Port(..) => 4,
+        Value::Pid(..) => 5,
+        Value::Tuple(_) => 6,
+        Value::Map(_) => 7,
+        Value::Nil => 8,
+        Value::String([]) => 8,
+        Value::List(..) | Value::String(_) => LIST,
+        Value::Binary(_) => 10,
+    }
+}
+
+fn cmp_integer(a: (bool, &[u8]), b: (bool, &[u8])) -> Ordering {
+    let o =
+        a.1.len()
+            .cmp(&b.1.len())
+            .then_with(|| a.1.iter().rev().cmp(b.1.iter().rev()));
+    match (a.0, b.0) {
+        (false, false) => o,
+        (true, true) => o.reverse(),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+    }
+}
+
+fn cmp_all(a: &[Value], b: &[Value]) -> Ordering {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| cmp(x, y))
+        .find(|o| *o != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+#[derive(Clone, Copy)]
+enum Elem<'v, 'a> {
+    Value(&'v Value<'a>),
+    Byte(u8), // of a STRING_EXT
+}
+
+// a list an element at a time, on into its tail while that is a list too
+struct Walk<'v, 'a> {
+    values: &'v [Value<'a>],
+    bytes: &'v [u8],
+    tail: &'v Value<'a>,
+}
+
+impl<'v, 'a> Walk<'v, 'a> {
+    fn new(v: &'v Value<'a>) -> Self {
+        let mut walk = Walk {
+            values: &[],
+            bytes: &[],
+            tail: v,
+        };
+        walk.next_tail();
+        walk
+    }
+
+    // false once the tail is not a list
+    fn next_tail(&mut self) -> bool {
+        match self.tail {
+            Value::List(v, t) => (self.values, self.tail) = (v, t),
+            Value::String(b) => (self.bytes, self.tail) = (b, &NIL),
+            _ => return false,
+        }
+        true
+    }
+
+    fn next(&mut self) -> Option<Elem<'v, 'a>> {
+        loop {
+            if let Some((x, xs)) = self.values.split_first() {
+                self.values = xs;
+                return Some(Elem::Value(x));
+            }
+            if let Some((&u, us)) = self.bytes.split_first() {
+                self.bytes = us;
+                return Some(Elem::Byte(u));
+            }
+            if !self.next_tail() {
+                return None;
+            }
+        }
+    }
+}
+
+fn cmp_byte(u: u8, v: &Value) -> Ordering {
+    let b = [u];
+    match v {
+        Value::Integer(s, m) => cmp_integer((false, if u == 0 { &[] } else { &b }), (*s, m)),
+        Value::Float(_) => Ordering::Less,
+        v => 0.cmp(&rank(v)),
+    }
+}
+
+// a loop, not recursion, however long the lists and however their tails nest
+fn cmp_list(a: &Value, b: &Value) -> Ordering {
+    let (mut a, mut b) = (Walk::new(a), Walk::new(b));
+    loop {
+        let o = match (a.next(), b.next()) {
+            (Some(Elem::Byte(x)), Some(Elem::Byte(y))) => x.cmp(&y),
+            (Some(Elem::Byte(x)), Some(Elem::Value(y))) => cmp_byte(x, y),
+            (Some(Elem::Value(x)), Some(Elem::Byte(y))) => cmp_byte(y, x).reverse(),
+            (Some(Elem::Value(x)), Some(Elem::Value(y))) => cmp(x, y),
+            (None, None) => return cmp(a.tail, b.tail),
+            (None, Some(_)) => return rank(a.tail).cmp(&LIST),
+            (Some(_), None) => return LIST.cmp(&rank(b.tail)),
+        };
+        if o != Ordering::Equal {
+            return o;
+        }
+    }
+}
+
+fn sorted<'a, 'b>(m: &'b [(Value<'a>, Value<'a>)]) -> Vec<&'b (Value<'a>, Value<'a>)> {
+    let mut vec = m.iter().collect::<Vec<_>>();
+    vec.sort_by(|x, y| cmp(&x.0, &y.0));
+    vec
+}
+
+// exact term order, as used for map keys: all integers sort before all floats
+pub fn cmp(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Integer(s1, v1), Value::Integer(s2, v2)) => cmp_integer((*s1, v1), (*s2, v2)),
+        (Value::Integer(..), Value::Float(_)) => Ordering::Less,
+        (Value::Float(_), Value::Integer(..)) => Ordering::Greater,
+        (Value::Float(f1), Value::Float(f2)) => f1.total_cmp(f2),
+        (Value::Atom(s1), Value::Atom(s2)) => s1.cmp(s2),
+        (Value::Ref(n1, c1, i1), Value::Ref(n2, c2, i2)) => n1
+            .cmp(n2)
+            .then(c1.cmp(c2))
+            .then_with(|| i1.len().cmp(&i2.len()))
+            .then_with(|| i1.iter().rev().cmp(i2.iter().rev())),
+        (Value::Port(n1, i1, c1), Value::Port(n2, i2, c2)) => {
+            n1.cmp(n2).then(c1.cmp(c2)).then(i1.cmp(i2))
+        }
+        (Value::Pid(n1, i1, s1, c1), Value::Pid(n2, i2, s2, c2)) => n1
+            .cmp(n2)
+            .then(c1.cmp(c2))
+            .then(s1.cmp(s2))
+            .then(i1.cmp(i2)),
+        (Value::Tuple(v1), Value::Tuple(v2)) => {
+            v1.len().cmp(&v2.len()).then_with(|| cmp_all(v1, v2))
+        }
+        (Value::Map(m1), Value::Map(m2)) => m1.len().cmp(&m2.len()).then_with(|| {
+            let (m1, m2) = (sorted(m1), sorted(m2));
+            let k = m1.iter().zip(m2.iter()).map(|(x, y)| cmp(&x.0, &y.0));
+            let v = m1.iter().zip(m2.iter()).map(|(x, y)| cmp(&x.1, &y.1));
+            k.chain(v)
+                .find(|o| *o != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        }),
+        (Value::List(..) | Value::String(_), Value::List(..) | Value::String(_))
+            if rank(a) == LIST && rank(b) == LIST =>
+        {
+            cmp_list(a, b)
+        }
+        (Value::Binary(b1), Value::Binary(b2)) => b1.cmp(b2),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use std::cmp::Ordering;
+
+    use crate::value::parse;
+
+    use super::cmp;
+
+    #[test]
+    fn list() {
+        for (a, b, expected) in vec![
+            // "ab" and [$a, $b]
+            (vec![0x6b, 0x00, 0x02, 0x61, 0x62], vec![0x6c, 0x00, 0x00, 0x00, 0x02, 0x61, 0x61, 0x61, 0x62, 0x6a], Ordering::Equal),
+            // "ab" and [$a, 256]
+            (vec![0x6b, 0x00, 0x02, 0x61, 0x62], vec![0x6c, 0x00, 0x00, 0x00, 0x02, 0x61, 0x61, 0x62, 0x00, 0x00, 0x01, 0x00, 0x6a], Ordering::Less),
+            // "ab" and [$a, 1.0]
+            (vec![0x6b, 0x00, 0x02, 0x61, 0x62], vec![0x6c, 0x00, 0x00, 0x00, 0x02, 0x61, 0x61, 0x46, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x6a], Ordering::Less),
+            // "a" and "ab"
+            (vec![0x6b, 0x00, 0x01, 0x61], vec![0x6b, 0x00, 0x02, 0x61, 0x62], Ordering::Less),
+            // [$a | "b"] and "ab"
+            (vec![0x6c, 0x00, 0x00, 0x00, 0x01, 0x61, 0x61, 0x6b, 0x00, 0x01, 0x62], vec![0x6b, 0x00, 0x02, 0x61, 0x62], Ordering::Equal),
+            // [$a | b] and "ab"
+            (vec![0x6c, 0x00, 0x00, 0x00, 0x01, 0x61, 0x61, 0x77, 0x01, 0x62], vec![0x6b, 0x00, 0x02, 0x61, 0x62], Ordering::Less),
+            // [] and "a"
+            (vec![0x6a], vec![0x6b, 0x00, 0x01, 0x61], Ordering::Less),
+            // "a" and <<"a">>
+            (vec![0x6b, 0x00, 0x01, 0x61], vec![0x6d, 0x00, 0x00, 0x00, 0x01, 0x61], Ordering::Less),
+        ] {
+            let (x, y) = (parse(&a).unwrap().0, parse(&b).unwrap().0);
+            assert_eq!(expected, cmp(&x, &y), "{:?} {:?}", a, b);
+            assert_eq!(expected.reverse(), cmp(&y, &x), "{:?} {:?}", a, b);
+        }
+    }
+}
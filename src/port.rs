@@ -1,32 +1,495 @@
-use std::io;
+#[cfg(unix)]
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+#[cfg(unix)]
+use std::os::unix::io::{FromRawFd, RawFd};
+#[cfg(unix)]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{de, ser};
 
 use crate::consts::*;
+use crate::de::Deserializer;
 use crate::error::Error;
 use crate::io::{Reader, Writer};
+use crate::ser::Serializer;
+
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "tokio")]
+pub use self::async_io::{AsyncReader, AsyncWriter};
+#[cfg(feature = "tokio")]
+mod codec;
+#[cfg(feature = "tokio")]
+pub use self::codec::{PacketCodec, TermCodec};
+mod export;
+pub use self::export::{Export, Exports};
+mod sender;
+pub use self::sender::PortSender;
+mod server;
+pub use self::server::{Handler, Server};
+
+/// open_port(_, [nouse_stdio]): the port reads from fd 3 and writes to fd 4, leaving
+/// stdout and stderr free for logging. The descriptors can be taken only once, and are
+/// closed when the reader and writer are dropped.
+///
+/// # Safety
+///
+/// The process must have been spawned by open_port with nouse_stdio, and nothing else in
+/// it may own or have closed fd 3 or fd 4: either may otherwise be a file the program
+/// opened itself, which dropping the reader or writer would close under its owner.
+#[cfg(unix)]
+pub unsafe fn nouse_stdio() -> Result<(PortReader<BufReader<File>>, PortWriter<File>), Error> {
+    static TAKEN: AtomicBool = AtomicBool::new(false);
+    if TAKEN.swap(true, Ordering::SeqCst) {
+        return Err(invalid_input!("nouse_stdio: taken"));
+    }
+    // SAFETY: the caller vouches that the emulator opened both for this program and
+    //  nothing else owns them; TAKEN keeps them from being wrapped twice
+    Ok(unsafe { from_raw_fds(3, 4) })
+}
+
+// SAFETY: the caller owns both descriptors, open for reading and writing, and gives them up
+#[cfg(unix)]
+unsafe fn from_raw_fds(r: RawFd, w: RawFd) -> (PortReader<BufReader<File>>, PortWriter<File>) {
+    (
+        PortReader::new(BufReader::new(File::from_raw_fd(r))),
+        PortWriter::new(File::from_raw_fd(w)),
+    )
+}
+
+// a Reader framed as a port sees its input: {packet, N}, a stream of terms, or lines
+pub struct PortReader<R> {
+    reader: Reader<R>,
+    packet: usize,
+}
+
+// a Writer framed as a port expects its output
+pub struct PortWriter<W> {
+    writer: Writer<W>,
+    packet: usize,
+    buf: Vec<u8>, // reused by send_term
+}
+
+// a line as a {line, N} port sees it, noeol when cut at N bytes or by the end of stream
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Line {
+    Eol(Vec<u8>),
+    Noeol(Vec<u8>),
+}
+
+enum Frame {
+    Empty,
+    Packet(usize),
+    Stream,
+}
 
 impl<R> Reader<R>
 where
     R: io::Read,
 {
+    // {packet, 2}
     pub fn recv(&mut self) -> Result<Vec<u8>, Error> {
-        let u = self.read_u16()?;
+        PortReader::new(self.get_mut()).recv()
+    }
+}
 
-        if self.read_u8()? != ERL_VERSION_MAGIC {
+impl<W> Writer<W>
+where
+    W: io::Write,
+{
+    // {packet, 2}
+    pub fn send(&mut self, v: &[u8]) -> Result<(), Error> {
+        PortWriter::new(self.get_mut()).send(v)
+    }
+}
+
+impl<R> PortReader<R>
+where
+    R: io::Read,
+{
+    pub fn new(r: R) -> Self {
+        PortReader {
+            reader: Reader::new(r),
+            packet: 2,
+        }
+    }
+
+    // {packet, N}: 1, 2 (default) or 4, 0 for a raw stream of terms
+    pub fn packet(mut self, n: usize) -> Self {
+        self.packet = n;
+        self
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.reader.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.reader.get_mut()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    pub fn recv(&mut self) -> Result<Vec<u8>, Error> {
+        self.recv_opt()?
+            .ok_or_else(|| Error::Io(io::ErrorKind::UnexpectedEof.into()))
+    }
+
+    // None at the end of input: a closed stream, or an empty packet as
+    //  port_command(Port, <<>>) sends
+    pub fn recv_opt(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let u = match self.reader.read_opt_u8()? {
+            Some(u) => u,
+            None => return Ok(None),
+        };
+        match self.recv_len(u)? {
+            Frame::Empty => Ok(None),
+            Frame::Packet(u) => self.reader.read_exact_usize(u).map(Some),
+            Frame::Stream => {
+                let mut vec = Vec::new();
+                self.reader.read_term(&mut vec).map(|()| Some(vec))
+            }
+        }
+    }
+
+    // decoded straight from the stream, the term must fill the packet
+    pub fn recv_term<T>(&mut self) -> Result<T, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        let u = self.reader.read_u8()?;
+        match self.recv_len(u)? {
+            Frame::Empty => Err(invalid_data!("recv_term: {}", 0)),
+            Frame::Packet(u) => {
+                let mut r = self.reader.get_mut().take(u as u64);
+                let mut de = Deserializer::new(&mut r);
+                let t = de::Deserialize::deserialize(&mut de).and_then(|t| de.end().map(|()| t));
+                // whatever a bad term left of the packet, so the next one is framed right
+                io::copy(&mut r, &mut io::sink()).map_err(Error::Io)?;
+                t
+            }
+            Frame::Stream => {
+                de::Deserialize::deserialize(&mut Deserializer::new(self.reader.get_mut()))
+            }
+        }
+    }
+
+    // as recv_opt, with the version magic left in place: a packet is read whole
+    //  whatever it holds, so a bad one does not lose the framing of the next
+    pub(crate) fn recv_packet(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let u = match self.reader.read_opt_u8()? {
+            Some(u) => u,
+            None => return Ok(None),
+        };
+        if self.packet == 0 {
+            self.recv_len(u)?;
+            let mut vec = vec![ERL_VERSION_MAGIC];
+            return self.reader.read_term(&mut vec).map(|()| Some(vec));
+        }
+        match self.recv_size(u)? {
+            0 => Ok(None),
+            u => self.reader.read_exact_usize(u).map(Some),
+        }
+    }
+
+    // what follows the first byte u, up to the version magic
+    fn recv_len(&mut self, u: u8) -> Result<Frame, Error> {
+        if self.packet == 0 {
+            if u != ERL_VERSION_MAGIC {
+                return Err(invalid_data!("ERL_VERSION_MAGIC"));
+            }
+            return Ok(Frame::Stream);
+        }
+
+        let u = self.recv_size(u)?;
+        if u == 0 {
+            return Ok(Frame::Empty);
+        }
+
+        if self.reader.read_u8()? != ERL_VERSION_MAGIC {
             return Err(invalid_data!("ERL_VERSION_MAGIC"));
         }
 
-        self.read_exact_usize(u as usize - 1)
+        Ok(Frame::Packet(u - 1))
+    }
+
+    // the packet length, u being its first byte
+    fn recv_size(&mut self, u: u8) -> Result<usize, Error> {
+        match self.packet {
+            n @ (1 | 2 | 4) => Ok(self
+                .reader
+                .read_exact_usize(n - 1)?
+                .iter()
+                .fold(u as usize, |a, &b| (a << 8) | b as usize)),
+            n => Err(invalid_input!("packet: {}", n)),
+        }
+    }
+}
+
+impl<R> PortReader<R>
+where
+    R: io::BufRead,
+{
+    // {line, N}: at most n bytes, without the newline. Read through the buffer of R,
+    //  io::stdin().lock() or an io::BufReader
+    pub fn recv_line(&mut self, n: usize) -> Result<Line, Error> {
+        let mut vec = Vec::new();
+        let mut r = self.get_mut().take(n as u64);
+        match r.read_until(b'\n', &mut vec).map_err(Error::Io)? {
+            0 if n > 0 => Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+            _ if vec.last() == Some(&b'\n') => {
+                vec.pop();
+                Ok(Line::Eol(vec))
+            }
+            _ => Ok(Line::Noeol(vec)),
+        }
     }
 }
 
-impl<W> Writer<W>
+impl<W> PortWriter<W>
 where
     W: io::Write,
 {
+    pub fn new(w: W) -> Self {
+        PortWriter {
+            writer: Writer::new(w),
+            packet: 2,
+            buf: Vec::new(),
+        }
+    }
+
+    // {packet, N}: 1, 2 (default) or 4, 0 for a raw stream of terms
+    pub fn packet(mut self, n: usize) -> Self {
+        self.packet = n;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
     pub fn send(&mut self, v: &[u8]) -> Result<(), Error> {
-        self.write_u16(v.len() as u16 + 1)?;
-        self.write_u8(ERL_VERSION_MAGIC)?;
-        self.write_all(v)?;
-        self.flush()
+        let mut len = [0u8; 4];
+        let n = self.send_len(v.len() + 1, &mut len)?;
+        self.writer.write_all(&len[..n])?;
+        self.writer.write_u8(ERL_VERSION_MAGIC)?;
+        self.writer.write_all(v)?;
+        self.writer.flush()
+    }
+
+    // encoded into a buffer kept across calls, the length written in front afterwards
+    pub fn send_term<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        let mut buf = std::mem::take(&mut self.buf);
+        let r = self.send_buf(&mut buf, value);
+        self.buf = buf;
+        r
+    }
+
+    fn send_buf<T>(&mut self, buf: &mut Vec<u8>, value: &T) -> Result<(), Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        let n = match self.packet {
+            n @ (0 | 1 | 2 | 4) => n,
+            n => return Err(invalid_input!("packet: {}", n)),
+        };
+        buf.clear();
+        buf.resize(n, 0);
+        buf.push(ERL_VERSION_MAGIC);
+        value.serialize(&mut Serializer::new(&mut *buf))?;
+        let mut len = [0u8; 4];
+        self.send_len(buf.len() - n, &mut len)?;
+        buf[..n].copy_from_slice(&len[..n]);
+        self.writer.write_all(buf)?;
+        self.writer.flush()
+    }
+
+    fn send_len(&self, u: usize, len: &mut [u8; 4]) -> Result<usize, Error> {
+        send_len(self.packet, u, len)
+    }
+
+    pub fn send_line(&mut self, v: &[u8]) -> Result<(), Error> {
+        if v.contains(&b'\n') {
+            return Err(invalid_input!("send_line: {}", v.len()));
+        }
+        self.writer.write_all(v)?;
+        self.writer.write_u8(b'\n')?;
+        self.writer.flush()
+    }
+}
+
+// the {packet, N} prefix for u bytes, in the first N bytes of len
+fn send_len(packet: usize, u: usize, len: &mut [u8; 4]) -> Result<usize, Error> {
+    match packet {
+        0 => Ok(0),
+        1 if u <= u8::MAX as usize => Ok(write_len(len, &(u as u8).to_be_bytes())),
+        2 if u <= u16::MAX as usize => Ok(write_len(len, &(u as u16).to_be_bytes())),
+        4 if u <= u32::MAX as usize => Ok(write_len(len, &(u as u32).to_be_bytes())),
+        n @ (1 | 2 | 4) => Err(invalid_input!("send: {}, {}", n, u)),
+        n => Err(invalid_input!("packet: {}", n)),
+    }
+}
+
+fn write_len(len: &mut [u8; 4], v: &[u8]) -> usize {
+    len[..v.len()].copy_from_slice(v);
+    v.len()
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use crate::{Reader, Writer};
+
+    use super::{Line, PortReader, PortWriter};
+
+    #[test]
+    fn packet() {
+        for (packet, expected) in vec![
+            (1, vec![0x03, 0x83, 0x61, 0x01]),
+            (2, vec![0x00, 0x03, 0x83, 0x61, 0x01]),
+            (4, vec![0x00, 0x00, 0x00, 0x03, 0x83, 0x61, 0x01]),
+        ] {
+            let mut writer = PortWriter::new(Vec::new()).packet(packet);
+            writer.send(&[0x61, 0x01]).unwrap();
+            let actual = writer.into_inner();
+            assert_eq!(expected, actual, "{}", packet);
+
+            let mut reader = PortReader::new(actual.as_slice()).packet(packet);
+            assert_eq!(vec![0x61, 0x01], reader.recv().unwrap(), "{}", packet);
+            assert!(reader.recv().is_err(), "{}", packet);
+        }
+
+        // {packet, 2} on a plain Reader and Writer
+        let mut writer = Writer::new(Vec::new());
+        writer.send(&[0x61, 0x01]).unwrap();
+        let actual = writer.into_inner();
+        assert_eq!(vec![0x00, 0x03, 0x83, 0x61, 0x01], actual);
+        assert_eq!(vec![0x61, 0x01], Reader::new(actual.as_slice()).recv().unwrap());
+    }
+
+    #[test]
+    fn packet_overflow() {
+        for (packet, len) in vec![
+            (1, u8::MAX as usize),
+            (2, u16::MAX as usize),
+        ] {
+            let mut writer = PortWriter::new(Vec::new()).packet(packet);
+            assert!(writer.send(&vec![0; len - 1]).is_ok(), "{}", packet);
+            assert!(writer.send(&vec![0; len]).is_err(), "{}", packet);
+            assert_eq!(packet + len, writer.into_inner().len(), "{}", packet);
+        }
+        let mut writer = PortWriter::new(Vec::new()).packet(4);
+        assert!(writer.send(&vec![0; u16::MAX as usize]).is_ok());
+
+        assert!(PortWriter::new(Vec::new()).packet(3).send(&[0x6a]).is_err());
+        assert!(PortReader::new(&[0x00, 0x00][..]).recv().is_err());
+        assert_eq!(None, PortReader::new(&[0x00, 0x00][..]).recv_opt().unwrap());
+        assert_eq!(None, PortReader::new(&[][..]).recv_opt().unwrap());
+        assert!(PortReader::new(&[0x00][..]).recv_opt().is_err());
+        assert!(PortReader::new(&[0x00, 0x01, 0x83][..]).packet(3).recv().is_err());
+    }
+
+    #[test]
+    fn stream() {
+        let mut writer = PortWriter::new(Vec::new()).packet(0);
+        writer.send(&[0x68, 0x01, 0x61, 0x01]).unwrap();
+        writer.send(&[0x6a]).unwrap();
+        let actual = writer.into_inner();
+        assert_eq!(vec![0x83, 0x68, 0x01, 0x61, 0x01, 0x83, 0x6a], actual);
+
+        let mut reader = PortReader::new(actual.as_slice()).packet(0);
+        assert_eq!(vec![0x68, 0x01, 0x61, 0x01], reader.recv().unwrap());
+        assert_eq!(vec![0x6a], reader.recv().unwrap());
+        assert!(reader.recv().is_err());
+
+        assert!(PortReader::new(&[0x83, 0x68, 0x02, 0x61][..]).packet(0).recv().is_err());
+        assert!(PortReader::new(&[0x68, 0x00][..]).packet(0).recv().is_err());
+    }
+
+    #[test]
+    fn term() {
+        for (packet, expected) in vec![
+            (0, vec![0x83, 0x68, 0x02, 0x61, 0x01, 0x6a]),
+            (1, vec![0x06, 0x83, 0x68, 0x02, 0x61, 0x01, 0x6a]),
+            (2, vec![0x00, 0x06, 0x83, 0x68, 0x02, 0x61, 0x01, 0x6a]),
+            (4, vec![0x00, 0x00, 0x00, 0x06, 0x83, 0x68, 0x02, 0x61, 0x01, 0x6a]),
+        ] {
+            let mut writer = PortWriter::new(Vec::new()).packet(packet);
+            writer.send_term(&(1u8, ())).unwrap();
+            writer.send_term(&(1u8, ())).unwrap();
+            let actual = writer.into_inner();
+            assert_eq!(expected.repeat(2), actual, "{}", packet);
+
+            let mut reader = PortReader::new(actual.as_slice()).packet(packet);
+            for _ in 0..2 {
+                assert_eq!((1u8, ()), reader.recv_term().unwrap(), "{}", packet);
+            }
+            assert!(reader.recv_term::<(u8, ())>().is_err(), "{}", packet);
+        }
+
+        let mut writer = PortWriter::new(Vec::new()).packet(1);
+        assert!(writer.send_term(&vec![0u8; 255]).is_err());
+        writer.send_term(&0u8).unwrap();
+        assert_eq!(vec![0x03, 0x83, 0x61, 0x00], writer.into_inner());
+
+        // trailing bytes in the packet
+        let input = vec![0x00, 0x04, 0x83, 0x61, 0x01, 0x6a];
+        assert!(PortReader::new(input.as_slice()).recv_term::<u8>().is_err());
+        // a packet shorter than the term
+        let input = vec![0x00, 0x03, 0x83, 0x68, 0x02, 0x61, 0x01, 0x6a];
+        assert!(PortReader::new(input.as_slice()).recv_term::<(u8, ())>().is_err());
+
+        // a bad packet, {1, 2} for a u8, then a good one
+        let input = vec![0x00, 0x07, 0x83, 0x68, 0x02, 0x61, 0x01, 0x61, 0x02, 0x00, 0x03, 0x83, 0x61, 0x05];
+        let mut reader = PortReader::new(input.as_slice());
+        assert!(reader.recv_term::<u8>().is_err());
+        assert_eq!(5u8, reader.recv_term().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn raw_fds() {
+        use std::os::unix::io::IntoRawFd;
+
+        let (r, w) = std::io::pipe().unwrap();
+        let (mut reader, mut writer) = unsafe { super::from_raw_fds(r.into_raw_fd(), w.into_raw_fd()) };
+        writer.send_term(&(1u8, ())).unwrap();
+        writer.send_line(b"abc").unwrap();
+        drop(writer);
+        assert_eq!((1u8, ()), reader.recv_term().unwrap());
+        assert_eq!(Line::Eol(b"abc".to_vec()), reader.recv_line(8).unwrap());
+        assert_eq!(None, reader.recv_opt().unwrap());
+    }
+
+    #[test]
+    fn line() {
+        let mut writer = PortWriter::new(Vec::new());
+        writer.send_line(b"abc").unwrap();
+        writer.send_line(b"").unwrap();
+        assert!(writer.send_line(b"a\nb").is_err());
+        let actual = writer.into_inner();
+        assert_eq!(b"abc\n\n".to_vec(), actual);
+
+        let input = b"abcdef\nabc\n\nab";
+        let mut reader = PortReader::new(&input[..]);
+        for expected in vec![
+            Line::Noeol(b"abcd".to_vec()),
+            Line::Eol(b"ef".to_vec()),
+            Line::Eol(b"abc".to_vec()),
+            Line::Eol(b"".to_vec()),
+            Line::Noeol(b"ab".to_vec()),
+        ] {
+            assert_eq!(expected, reader.recv_line(4).unwrap());
+        }
+        assert!(reader.recv_line(4).is_err());
+        assert_eq!(Line::Noeol(vec![]), PortReader::new(&b"a\n"[..]).recv_line(0).unwrap());
     }
 }
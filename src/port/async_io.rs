@@ -0,0 +1,207 @@
+use std::io;
+
+use bytes::BytesMut;
+use serde::{de, ser};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::consts::*;
+use crate::de::from_slice;
+use crate::error::Error;
+
+use super::codec::{encode, Frames};
+use super::send_len;
+
+// Reader over tokio's AsyncRead: {packet, N} as PortReader::new takes it. Input is read
+//  ahead into a buffer, so the inner reader is lent out but never handed back
+pub struct AsyncReader<R> {
+    r: R,
+    frames: Frames,
+    buf: BytesMut,
+}
+
+impl<R> AsyncReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    pub fn new(r: R) -> Self {
+        AsyncReader {
+            r,
+            frames: Frames::new(2),
+            buf: BytesMut::new(),
+        }
+    }
+
+    pub fn packet(mut self, n: usize) -> Self {
+        self.frames = self.frames.packet(n);
+        self
+    }
+
+    // as PacketCodec::max_frame_len
+    pub fn max_frame_len(mut self, n: usize) -> Self {
+        self.frames = self.frames.max_frame_len(n);
+        self
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.r
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.r
+    }
+
+    pub async fn recv(&mut self) -> Result<Vec<u8>, Error> {
+        self.recv_opt()
+            .await?
+            .ok_or_else(|| Error::Io(io::ErrorKind::UnexpectedEof.into()))
+    }
+
+    // as PortReader::recv_opt
+    pub async fn recv_opt(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        match self.recv_packet().await? {
+            Some(v) if v.is_empty() => Ok(None),
+            Some(v) if v[0] == ERL_VERSION_MAGIC => Ok(Some(v[1..].to_vec())),
+            Some(_) => Err(invalid_data!("ERL_VERSION_MAGIC")),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn recv_term<T>(&mut self) -> Result<T, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        match self.recv_packet().await? {
+            Some(v) if v.is_empty() => Err(invalid_data!("recv_term: {}", 0)),
+            Some(v) => from_slice(&v, true),
+            None => Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+        }
+    }
+
+    async fn recv_packet(&mut self) -> Result<Option<BytesMut>, Error> {
+        loop {
+            if let Some(v) = self.frames.decode(&mut self.buf)? {
+                return Ok(Some(v));
+            }
+            if self.r.read_buf(&mut self.buf).await.map_err(Error::Io)? == 0 {
+                return match self.buf.len() {
+                    0 => Ok(None),
+                    _ => Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+                };
+            }
+        }
+    }
+}
+
+// Writer over tokio's AsyncWrite, each frame written whole and flushed
+pub struct AsyncWriter<W> {
+    w: W,
+    packet: usize,
+    buf: BytesMut, // reused by send and send_term
+}
+
+impl<W> AsyncWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(w: W) -> Self {
+        AsyncWriter {
+            w,
+            packet: 2,
+            buf: BytesMut::new(),
+        }
+    }
+
+    pub fn packet(mut self, n: usize) -> Self {
+        self.packet = n;
+        self
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.w
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    pub async fn send(&mut self, v: &[u8]) -> Result<(), Error> {
+        let mut len = [0u8; 4];
+        let n = send_len(self.packet, v.len() + 1, &mut len)?;
+        self.buf.clear();
+        self.buf.extend_from_slice(&len[..n]);
+        self.buf.extend_from_slice(&[ERL_VERSION_MAGIC]);
+        self.buf.extend_from_slice(v);
+        self.send_buf().await
+    }
+
+    pub async fn send_term<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        self.buf.clear();
+        encode(self.packet, value, &mut self.buf)?;
+        self.send_buf().await
+    }
+
+    async fn send_buf(&mut self) -> Result<(), Error> {
+        self.w.write_all(&self.buf).await.map_err(Error::Io)?;
+        self.w.flush().await.map_err(Error::Io)
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use super::{AsyncReader, AsyncWriter};
+
+    #[tokio::test]
+    async fn packet() {
+        for (packet, expected) in vec![
+            (0, vec![0x83, 0x61, 0x01]),
+            (1, vec![0x03, 0x83, 0x61, 0x01]),
+            (2, vec![0x00, 0x03, 0x83, 0x61, 0x01]),
+            (4, vec![0x00, 0x00, 0x00, 0x03, 0x83, 0x61, 0x01]),
+        ] {
+            let mut writer = AsyncWriter::new(Vec::new()).packet(packet);
+            writer.send(&[0x61, 0x01]).await.unwrap();
+            let actual = writer.into_inner();
+            assert_eq!(expected, actual, "{}", packet);
+
+            let mut reader = AsyncReader::new(actual.as_slice()).packet(packet);
+            assert_eq!(vec![0x61, 0x01], reader.recv().await.unwrap(), "{}", packet);
+            assert_eq!(None, reader.recv_opt().await.unwrap(), "{}", packet);
+        }
+
+        let mut reader = AsyncReader::new(&[0x00, 0x03, 0x83, 0x61][..]);
+        assert!(reader.recv().await.is_err());
+
+        let mut reader = AsyncReader::new(&[0xff, 0xff, 0x83][..]).max_frame_len(0xfffe);
+        assert!(reader.recv().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn term() {
+        for packet in vec![0, 1, 2, 4] {
+            let mut writer = AsyncWriter::new(Vec::new()).packet(packet);
+            writer.send_term(&(1, "a")).await.unwrap();
+            writer.send_term(&(2, "b")).await.unwrap();
+            let input = writer.into_inner();
+
+            // split across reads
+            let (r, mut w) = tokio::io::duplex(3);
+            let task = tokio::spawn(async move {
+                tokio::io::AsyncWriteExt::write_all(&mut w, &input).await
+            });
+            let mut reader = AsyncReader::new(r).packet(packet);
+            assert_eq!((1, "a".to_string()), reader.recv_term::<(u8, String)>().await.unwrap(), "{}", packet);
+            assert_eq!((2, "b".to_string()), reader.recv_term::<(u8, String)>().await.unwrap(), "{}", packet);
+            task.await.unwrap().unwrap();
+        }
+
+        let mut writer = AsyncWriter::new(Vec::new()).packet(1);
+        assert!(writer.send_term(&vec![0u8; 255]).await.is_err());
+        assert!(writer.into_inner().is_empty());
+    }
+}
@@ -0,0 +1,394 @@
+use std::io;
+use std::marker::PhantomData;
+
+use bytes::{BufMut, BytesMut};
+use serde::{de, ser};
+use tokio_util::codec;
+
+use crate::consts::*;
+use crate::de::from_slice;
+use crate::dec::{Decoder, Tag};
+use crate::error::Error;
+use crate::ser::Serializer;
+
+use super::send_len;
+
+// {packet, N} framed terms for tokio_util::codec::Framed, N as PortReader::packet takes it.
+//  A frame is the term without the version magic, as PortReader::recv_opt returns it;
+//  an empty packet, the end of input to recv_opt, is an empty frame
+#[derive(Clone, Copy, Debug)]
+pub struct PacketCodec {
+    frames: Frames,
+}
+
+impl PacketCodec {
+    pub fn new(packet: usize) -> Self {
+        PacketCodec {
+            frames: Frames::new(packet),
+        }
+    }
+
+    // the longest packet taken, the version magic included; 8 MiB by default
+    pub fn max_frame_len(mut self, n: usize) -> Self {
+        self.frames = self.frames.max_frame_len(n);
+        self
+    }
+}
+
+impl Default for PacketCodec {
+    fn default() -> Self {
+        PacketCodec::new(2)
+    }
+}
+
+impl codec::Decoder for PacketCodec {
+    type Item = Vec<u8>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.frames.decode(src)? {
+            Some(v) if v.is_empty() => Ok(Some(Vec::new())),
+            Some(v) if v[0] == ERL_VERSION_MAGIC => Ok(Some(v[1..].to_vec())),
+            Some(_) => Err(invalid_data!("ERL_VERSION_MAGIC")),
+            None => Ok(None),
+        }
+    }
+}
+
+impl codec::Encoder<&[u8]> for PacketCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: &[u8], dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut len = [0u8; 4];
+        let n = send_len(self.frames.packet, item.len() + 1, &mut len)?;
+        dst.reserve(n + 1 + item.len());
+        dst.put_slice(&len[..n]);
+        dst.put_u8(ERL_VERSION_MAGIC);
+        dst.put_slice(item);
+        Ok(())
+    }
+}
+
+// as PacketCodec, decoding frames into D and encoding E; an empty packet is an error
+pub struct TermCodec<D, E = D> {
+    frames: Frames,
+    phantom: PhantomData<fn(E) -> D>,
+}
+
+impl<D, E> TermCodec<D, E> {
+    pub fn new(packet: usize) -> Self {
+        TermCodec {
+            frames: Frames::new(packet),
+            phantom: PhantomData,
+        }
+    }
+
+    // as PacketCodec::max_frame_len
+    pub fn max_frame_len(mut self, n: usize) -> Self {
+        self.frames = self.frames.max_frame_len(n);
+        self
+    }
+}
+
+impl<D, E> Default for TermCodec<D, E> {
+    fn default() -> Self {
+        TermCodec::new(2)
+    }
+}
+
+impl<D, E> codec::Decoder for TermCodec<D, E>
+where
+    D: de::DeserializeOwned,
+{
+    type Item = D;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.frames.decode(src)? {
+            Some(v) if v.is_empty() => Err(invalid_data!("recv_term: {}", 0)),
+            Some(v) => from_slice(&v, true).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<D, E> codec::Encoder<E> for TermCodec<D, E>
+where
+    E: ser::Serialize,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: E, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        encode(self.frames.packet, &item, dst)
+    }
+}
+
+// {packet, N} framing of a buffer that fills up between calls, packet 0 included:
+//  there the term is stepped over an item at a time and where it got to is kept,
+//  so each byte is looked at once however the term arrives
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Frames {
+    packet: usize,
+    max_frame_len: usize,
+    scanned: usize, // packet 0: the whole items seen so far, the version magic included
+    pending: usize, //  and the items still to come
+}
+
+impl Frames {
+    pub(crate) fn new(packet: usize) -> Self {
+        Frames {
+            packet,
+            max_frame_len: 8 * 1024 * 1024,
+            scanned: 0,
+            pending: 0,
+        }
+    }
+
+    pub(crate) fn packet(mut self, n: usize) -> Self {
+        self.packet = n;
+        self
+    }
+
+    pub(crate) fn max_frame_len(mut self, n: usize) -> Self {
+        self.max_frame_len = n;
+        self
+    }
+
+    // one whole packet, the version magic included, once src holds it; a length over
+    //  max_frame_len is refused before any room is made for it
+    pub(crate) fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>, Error> {
+        let (n, u) = match self.packet {
+            0 => match self.scan(src) {
+                Ok(Some(u)) => (0, u),
+                Ok(None) => return Ok(None),
+                Err(e) => {
+                    self.scanned = 0;
+                    return Err(e);
+                }
+            },
+            n @ (1 | 2 | 4) if src.len() < n => return Ok(None),
+            n @ (1 | 2 | 4) => (n, src[..n].iter().fold(0, |a, &b| (a << 8) | b as usize)),
+            n => return Err(invalid_input!("packet: {}", n)),
+        };
+        if u > self.max_frame_len {
+            return Err(invalid_data!("max_frame_len: {}", u));
+        }
+        if src.len() < n + u {
+            src.reserve(n + u - src.len());
+            return Ok(None);
+        }
+        let _ = src.split_to(n);
+        Ok(Some(src.split_to(u)))
+    }
+
+    // the length of the term src starts with, once it holds all of it
+    fn scan(&mut self, src: &mut BytesMut) -> Result<Option<usize>, Error> {
+        if self.scanned == 0 {
+            match src.first() {
+                None => return Ok(None),
+                Some(&ERL_VERSION_MAGIC) => (self.scanned, self.pending) = (1, 1),
+                Some(_) => return Err(invalid_data!("ERL_VERSION_MAGIC")),
+            }
+        }
+        while self.pending > 0 {
+            let (u, nested) = match item(&src[self.scanned..])? {
+                Some(t) => t,
+                None => return Ok(None),
+            };
+            if self.scanned + u > self.max_frame_len {
+                return Err(invalid_data!("max_frame_len: {}", self.scanned + u));
+            }
+            if src.len() < self.scanned + u {
+                src.reserve(self.scanned + u - src.len());
+                return Ok(None);
+            }
+            self.scanned += u;
+            self.pending = self.pending - 1 + nested;
+        }
+        Ok(Some(std::mem::take(&mut self.scanned)))
+    }
+}
+
+// the length of the item v starts with and the number of items nested in it, once v
+//  holds its header; what follows the header may not have arrived yet
+#[rustfmt::skip]
+fn item(v: &[u8]) -> Result<Option<(usize, usize)>, Error> {
+    let atom = |at: usize| match header(v, at)? {
+        Some((Tag::Atom, len)) => match v[at] {
+            ERL_SMALL_ATOM_EXT | ERL_SMALL_ATOM_UTF8_EXT => Ok(Some(at + 2 + len)),
+            _ => Ok(Some(at + 3 + len)),
+        },
+        Some((tag, _)) => Err(invalid_data!("item: {:?}", tag)),
+        None => Ok(None),
+    };
+    let (tag, len) = match header(v, 0)? {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+    let t = match tag {
+        Tag::SmallInteger => (Some(2), 0),
+        Tag::Integer      => (Some(5), 0),
+        Tag::SmallBig     => (Some(3 + len), 0),
+        Tag::LargeBig     => (Some(6 + len), 0),
+        Tag::Float        => (Some(9), 0),
+        Tag::Atom         => (atom(0)?, 0),
+        Tag::Ref          => (atom(3)?.map(|u| u + 4 + 4 * len), 0),
+        Tag::Pid          => (atom(1)?.map(|u| u + 12), 0),
+        Tag::Port if v[0] == ERL_V4_PORT_EXT => (atom(1)?.map(|u| u + 12), 0),
+        Tag::Port         => (atom(1)?.map(|u| u + 8), 0),
+        Tag::SmallTuple   => (Some(2), len),
+        Tag::LargeTuple   => (Some(5), len),
+        Tag::Map          => (Some(5), 2 * len),
+        Tag::Nil          => (Some(1), 0),
+        Tag::String       => (Some(3 + len), 0),
+        Tag::List         => (Some(5), len + 1),
+        Tag::Binary       => (Some(5 + len), 0),
+    };
+    Ok(t.0.map(|u| (u, t.1)))
+}
+
+// the tag and length at v[at..], None while they are cut short
+fn header(v: &[u8], at: usize) -> Result<Option<(Tag, usize)>, Error> {
+    match Decoder::new(v.get(at..).unwrap_or_default()).get_type() {
+        Ok(t) => Ok(Some(t)),
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// as PortWriter::send_term, the length written in front afterwards
+pub(crate) fn encode<T>(packet: usize, value: &T, dst: &mut BytesMut) -> Result<(), Error>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let n = match packet {
+        n @ (0 | 1 | 2 | 4) => n,
+        n => return Err(invalid_input!("packet: {}", n)),
+    };
+    let start = dst.len();
+    dst.put_bytes(0, n);
+    dst.put_u8(ERL_VERSION_MAGIC);
+    let r = value.serialize(&mut Serializer::new((&mut *dst).writer()));
+    let mut len = [0u8; 4];
+    match r.and_then(|()| send_len(packet, dst.len() - start - n, &mut len)) {
+        Ok(_) => {
+            dst[start..start + n].copy_from_slice(&len[..n]);
+            Ok(())
+        }
+        Err(e) => {
+            dst.truncate(start);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::{PacketCodec, TermCodec};
+
+    #[test]
+    fn packet() {
+        for (packet, expected) in vec![
+            (0, vec![0x83, 0x61, 0x01]),
+            (1, vec![0x03, 0x83, 0x61, 0x01]),
+            (2, vec![0x00, 0x03, 0x83, 0x61, 0x01]),
+            (4, vec![0x00, 0x00, 0x00, 0x03, 0x83, 0x61, 0x01]),
+        ] {
+            let mut codec = PacketCodec::new(packet);
+            let mut buf = BytesMut::new();
+            codec.encode(&[0x61, 0x01][..], &mut buf).unwrap();
+            assert_eq!(expected, buf.to_vec(), "{}", packet);
+
+            // a byte at a time
+            let mut src = BytesMut::new();
+            for &u in &expected {
+                assert_eq!(None, codec.decode(&mut src).unwrap(), "{}", packet);
+                src.extend_from_slice(&[u]);
+            }
+            assert_eq!(Some(vec![0x61, 0x01]), codec.decode(&mut src).unwrap(), "{}", packet);
+            assert!(src.is_empty(), "{}", packet);
+        }
+
+        let mut src = BytesMut::from(&[0x00, 0x00, 0x00, 0x02, 0x84, 0x61][..]);
+        let mut codec = PacketCodec::default();
+        assert_eq!(Some(vec![]), codec.decode(&mut src).unwrap());
+        assert!(codec.decode(&mut src).is_err());
+        assert!(src.is_empty());
+
+        let mut buf = BytesMut::new();
+        assert!(PacketCodec::new(1).encode(&[0u8; 255][..], &mut buf).is_err());
+        assert!(PacketCodec::new(3).encode(&[0u8; 1][..], &mut buf).is_err());
+    }
+
+    #[test]
+    fn term() {
+        for packet in vec![0, 1, 2, 4] {
+            let mut codec = TermCodec::<(u8, String)>::new(packet);
+            let mut buf = BytesMut::new();
+            codec.encode((1, "a".to_string()), &mut buf).unwrap();
+            codec.encode((2, "b".to_string()), &mut buf).unwrap();
+            assert_eq!(Some((1, "a".to_string())), codec.decode(&mut buf).unwrap(), "{}", packet);
+            assert_eq!(Some((2, "b".to_string())), codec.decode(&mut buf).unwrap(), "{}", packet);
+            assert_eq!(None, codec.decode(&mut buf).unwrap(), "{}", packet);
+        }
+
+        let mut codec = TermCodec::<u8, &str>::default();
+        let mut buf = BytesMut::new();
+        codec.encode("ab", &mut buf).unwrap();
+        assert_eq!(vec![0x00, 0x06, 0x83, 0x6b, 0x00, 0x02, 0x61, 0x62], buf.to_vec());
+        assert!(TermCodec::<u8, Vec<u8>>::new(1).encode(vec![0u8; 255], &mut buf).is_err());
+        assert_eq!(vec![0x00, 0x06, 0x83, 0x6b, 0x00, 0x02, 0x61, 0x62], buf.to_vec());
+        assert!(codec.decode(&mut buf).is_err());
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from(&[0x00, 0x00][..]);
+        assert!(codec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn max_frame_len() {
+        // refused on the header alone, before room is made for the rest
+        for (packet, input) in vec![
+            (0, vec![0x83, 0x6d, 0x00, 0x00, 0x00, 0x05]),
+            (0, vec![0x83, 0x6c, 0x00, 0x00, 0x00, 0x02, 0x61, 0x01, 0x6d, 0x00, 0x00, 0x00, 0x02]),
+            (1, vec![0x0b]),
+            (2, vec![0x00, 0x0b]),
+            (4, vec![0x00, 0x00, 0x00, 0x0b]),
+        ] {
+            let mut codec = PacketCodec::new(packet).max_frame_len(10);
+            let mut src = BytesMut::from(input.as_slice());
+            assert!(codec.decode(&mut src).is_err(), "{}", packet);
+            assert!(src.capacity() < 16, "{}", packet);
+        }
+
+        let mut codec = TermCodec::<String, &str>::new(0).max_frame_len(10);
+        let mut buf = BytesMut::new();
+        codec.encode("abcdef", &mut buf).unwrap();
+        assert_eq!(Some("abcdef".to_string()), codec.decode(&mut buf).unwrap());
+        codec.encode("abcdefg", &mut buf).unwrap();
+        assert!(codec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn scan() {
+        // packet 0 picks up where the last call got to
+        let mut codec = PacketCodec::new(0);
+        let mut src = BytesMut::from(&[0x83, 0x68, 0x03, 0x61, 0x01, 0x6b, 0x00, 0x02, 0x61][..]);
+        assert_eq!(None, codec.decode(&mut src).unwrap());
+        assert_eq!((5, 2), (codec.frames.scanned, codec.frames.pending));
+        src.extend_from_slice(&[0x62, 0x6a]);
+        assert_eq!(Some(vec![0x68, 0x03, 0x61, 0x01, 0x6b, 0x00, 0x02, 0x61, 0x62, 0x6a]), codec.decode(&mut src).unwrap());
+        assert_eq!((0, 0), (codec.frames.scanned, codec.frames.pending));
+
+        let mut src = BytesMut::from(&[0x83, 0x68, 0x01, 0x00][..]);
+        assert!(codec.decode(&mut src).is_err());
+        let mut src = BytesMut::from(&[0x83, 0x61, 0x01][..]);
+        assert_eq!(Some(vec![0x61, 0x01]), codec.decode(&mut src).unwrap());
+    }
+}
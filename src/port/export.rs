@@ -0,0 +1,247 @@
+use std::fmt;
+
+use serde::{de, ser};
+
+use crate::atom::InternedAtom;
+use crate::binary::Binary;
+use crate::de::Deserializer;
+use crate::dec::Decoder;
+use crate::error::Error;
+use crate::raw::RawTerm;
+use crate::ser::to_vec;
+use crate::term::Atom;
+
+use super::Handler;
+
+type Call = fn(&mut Deserializer<&[u8]>) -> Result<Vec<u8>, Error>;
+
+// a function exported by #[port_export], called as {Name, Arg, ...} or {Code, Arg, ...}
+#[derive(Clone, Copy)]
+pub struct Export {
+    name: &'static str,
+    code: Option<i64>,
+    arity: usize,
+    call: Call,
+}
+
+impl Export {
+    pub const fn new(name: &'static str, code: Option<i64>, arity: usize, call: Call) -> Self {
+        Export {
+            name,
+            code,
+            arity,
+            call,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn code(&self) -> Option<i64> {
+        self.code
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    // #region generated code
+
+    #[doc(hidden)]
+    pub fn arg<T>(de: &mut Deserializer<&[u8]>) -> Result<T, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        T::deserialize(de)
+    }
+
+    // {ok, Value}
+    #[doc(hidden)]
+    pub fn ok<T>(v: &T) -> Result<Vec<u8>, Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        to_vec(&(InternedAtom::OK, v))
+    }
+
+    // {ok, Value} or {error, Reason}
+    #[doc(hidden)]
+    pub fn result<T, E>(r: Result<T, E>) -> Result<Vec<u8>, Error>
+    where
+        T: ser::Serialize,
+        E: ser::Serialize,
+    {
+        match r {
+            Ok(v) => to_vec(&(InternedAtom::OK, v)),
+            Err(e) => to_vec(&(InternedAtom::ERROR, e)),
+        }
+    }
+
+    // ok
+    #[doc(hidden)]
+    pub fn done() -> Result<Vec<u8>, Error> {
+        to_vec(&InternedAtom::OK)
+    }
+
+    // #endregion
+}
+
+impl fmt::Debug for Export {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Export")
+            .field("name", &self.name)
+            .field("code", &self.code)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+// the dispatch table of port_exports!; an unknown command is answered with {error, undef},
+//  a wrong number or type of arguments with {error, {badarg, Detail}}
+#[derive(Clone, Debug, Default)]
+pub struct Exports {
+    vec: Vec<Export>,
+}
+
+impl Exports {
+    pub fn new(vec: Vec<Export>) -> Self {
+        Exports { vec }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Export> {
+        self.vec.iter().find(|e| e.name == name)
+    }
+
+    fn find(&self, dec: &mut Decoder<'_>) -> Result<&Export, RawTerm> {
+        if let Ok(a) = dec.decode_atom() {
+            return self.get(a.as_str()).ok_or_else(undef);
+        }
+        match dec.decode_long() {
+            Ok(i) => self
+                .vec
+                .iter()
+                .find(|e| e.code == Some(i))
+                .ok_or_else(undef),
+            Err(_) => Err(badarg("expected a name or a code")),
+        }
+    }
+}
+
+fn undef() -> RawTerm {
+    RawTerm::from_value(&Atom::from("undef")).expect("undef")
+}
+
+// {badarg, <<Detail>>}
+fn badarg(detail: &str) -> RawTerm {
+    let reason = (Atom::from("badarg"), Binary(detail.as_bytes()));
+    RawTerm::from_value(&reason).expect("badarg")
+}
+
+impl Handler for Exports {
+    type Request = RawTerm;
+    type Response = RawTerm;
+    type Error = RawTerm;
+
+    fn handle(&self, req: Self::Request) -> Result<Self::Response, Self::Error> {
+        let mut dec = Decoder::new(req.as_bytes());
+        // a bare command takes no arguments
+        let arity = match dec.decode_tuple_header() {
+            Ok(0) => return Err(badarg("empty request")),
+            Ok(u) => u - 1,
+            Err(_) => 0,
+        };
+        let export = self.find(&mut dec)?;
+        if export.arity != arity {
+            let detail = format!(
+                "{}/{} called with {} arguments",
+                export.name, export.arity, arity
+            );
+            return Err(badarg(&detail));
+        }
+        let mut de = Deserializer::new(dec.remaining());
+        (export.call)(&mut de)
+            .and_then(RawTerm::from_vec)
+            .map_err(|e| badarg(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(clippy::useless_vec)]
+mod tests {
+
+    use crate::port::Handler;
+    use crate::{from_reader, port_export, to_vec, Atom, RawTerm};
+
+    #[port_export(code = 'a')]
+    fn add(v1: i64, v2: i64) -> i64 {
+        v1 + v2
+    }
+
+    #[port_export(name = "div", code = 100)]
+    fn divide(v1: i64, v2: i64) -> Result<i64, Atom> {
+        v1.checked_div(v2).ok_or_else(|| Atom::from("badarith"))
+    }
+
+    #[port_export(crate = "crate")]
+    fn ping() {}
+
+    #[port_export]
+    fn len(s: String, v: crate::Binary) -> usize {
+        s.len() + v.0.len()
+    }
+
+    type Pair = (i64, i64);
+
+    // argument types are resolved where the function is
+    #[port_export]
+    fn sum(p: self::Pair, q: Pair) -> i64 {
+        p.0 + p.1 + q.0 + q.1
+    }
+
+    fn call<T: serde::Serialize>(req: &T) -> Result<Vec<u8>, Vec<u8>> {
+        let exports = crate::port_exports![add, divide, ping, self::len, sum];
+        let req = from_reader(to_vec(req).unwrap().as_slice()).unwrap();
+        exports.handle(req).map(RawTerm::into_vec).map_err(RawTerm::into_vec)
+    }
+
+    // the reason without its detail
+    fn reason(v: Vec<u8>) -> Atom {
+        from_reader::<_, Atom>(v.as_slice())
+            .or_else(|_| from_reader::<_, (Atom, crate::Binary)>(v.as_slice()).map(|(a, _)| a))
+            .unwrap()
+    }
+
+    #[test]
+    fn export() {
+        assert_eq!("add", add::EXPORT.name());
+        assert_eq!(Some(0x61), add::EXPORT.code());
+        assert_eq!(2, add::EXPORT.arity());
+        assert_eq!("div", divide::EXPORT.name());
+
+        for (expected, actual) in vec![
+            (to_vec(&(crate::atom!("ok"), 3)).unwrap(), call(&(crate::atom!("add"), 1, 2))),
+            (to_vec(&(crate::atom!("ok"), 3)).unwrap(), call(&(b'a', 1, 2))),
+            (to_vec(&(crate::atom!("ok"), 2)).unwrap(), call(&(crate::atom!("div"), 4, 2))),
+            (to_vec(&(crate::atom!("error"), crate::atom!("badarith"))).unwrap(), call(&(100, 4, 0))),
+            (to_vec(&crate::atom!("ok")).unwrap(), call(&crate::atom!("ping"))),
+            (to_vec(&crate::atom!("ok")).unwrap(), call(&(crate::atom!("ping"),))),
+            (to_vec(&(crate::atom!("ok"), 4)).unwrap(), call(&(crate::atom!("len"), "ab", crate::Binary(vec![1, 2])))),
+            (to_vec(&(crate::atom!("ok"), 10)).unwrap(), call(&(crate::atom!("sum"), (1, 2), (3, 4)))),
+        ] {
+            assert_eq!(expected, actual.unwrap());
+        }
+
+        for (expected, actual) in vec![
+            ("undef", call(&(crate::atom!("divide"), 4, 2))),
+            ("undef", call(&(b'm', 1, 2))),
+            ("badarg", call(&(crate::atom!("add"), 1))),
+            ("badarg", call(&(crate::atom!("add"), 1, crate::atom!("two")))),
+            ("badarg", call(&("add", 1, 2))),
+            ("badarg", call(&())),
+        ] {
+            assert_eq!(Atom::from(expected), reason(actual.unwrap_err()));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+
+use serde::ser;
+
+use crate::error::Error;
+use crate::ser::to_vec;
+
+use super::PortWriter;
+
+// a cloneable handle on a PortWriter owned by a thread of its own: frames from any number of
+//  threads are queued, at most capacity of them, and written whole one after another.
+//  The thread returns the PortWriter once every handle is dropped, or the first error
+#[derive(Clone, Debug)]
+pub struct PortSender {
+    tx: SyncSender<Frame>,
+    stats: Arc<Stats>,
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    capacity: usize,
+    depth: AtomicUsize,
+    max_depth: AtomicUsize,
+    sent: AtomicUsize,
+}
+
+impl PortSender {
+    pub fn spawn<W>(
+        writer: PortWriter<W>,
+        capacity: usize,
+    ) -> (Self, thread::JoinHandle<Result<PortWriter<W>, Error>>)
+    where
+        W: io::Write + Send + 'static,
+    {
+        let (tx, rx) = mpsc::sync_channel::<Frame>(capacity);
+        let stats = Arc::new(Stats {
+            capacity,
+            ..Stats::default()
+        });
+        let s = Arc::clone(&stats);
+        let handle = thread::spawn(move || {
+            let mut writer = writer;
+            // on an error the frames still queued, and those of senders blocked on a full
+            //  queue, are dropped with rx and uncount themselves
+            for f in rx {
+                writer.send(&f.take())?;
+                s.sent.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(writer)
+        });
+        (PortSender { tx, stats }, handle)
+    }
+
+    // blocks while the queue is full
+    pub fn send(&self, v: &[u8]) -> Result<(), Error> {
+        let f = self.enqueue(v.to_vec());
+        self.tx.send(f).map_err(|_| closed())
+    }
+
+    // encoded on the calling thread
+    pub fn send_term<T>(&self, value: &T) -> Result<(), Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        let f = self.enqueue(to_vec(value)?);
+        self.tx.send(f).map_err(|_| closed())
+    }
+
+    // WouldBlock while the queue is full
+    pub fn try_send(&self, v: &[u8]) -> Result<(), Error> {
+        self.try_enqueue(v.to_vec())
+    }
+
+    pub fn try_send_term<T>(&self, value: &T) -> Result<(), Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        to_vec(value).and_then(|v| self.try_enqueue(v))
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.stats.capacity
+    }
+
+    // frames queued and not yet taken by the writer, those of senders blocked on a full
+    //  queue included
+    pub fn depth(&self) -> usize {
+        self.stats.depth.load(Ordering::SeqCst)
+    }
+
+    // the deepest the queue has been, at most capacity
+    pub fn max_depth(&self) -> usize {
+        self.stats.max_depth.load(Ordering::SeqCst)
+    }
+
+    // frames written
+    pub fn sent(&self) -> usize {
+        self.stats.sent.load(Ordering::SeqCst)
+    }
+
+    fn try_enqueue(&self, v: Vec<u8>) -> Result<(), Error> {
+        let f = self.enqueue(v);
+        self.tx.try_send(f).map_err(|e| match e {
+            TrySendError::Full(_) => Error::Io(io::ErrorKind::WouldBlock.into()),
+            TrySendError::Disconnected(_) => closed(),
+        })
+    }
+
+    // counted before the frame is queued, so the writer never takes the depth below zero
+    fn enqueue(&self, v: Vec<u8>) -> Frame {
+        let u = self.stats.depth.fetch_add(1, Ordering::SeqCst) + 1;
+        self.stats
+            .max_depth
+            .fetch_max(u.min(self.stats.capacity), Ordering::SeqCst);
+        Frame {
+            v,
+            stats: Arc::clone(&self.stats),
+        }
+    }
+}
+
+// a queued frame, counted in depth until the writer takes it or it is dropped unsent:
+//  refused by the queue, or still in it when the writer stops
+#[derive(Debug)]
+struct Frame {
+    v: Vec<u8>,
+    stats: Arc<Stats>,
+}
+
+impl Frame {
+    fn take(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.v)
+    }
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        self.stats.depth.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn closed() -> Error {
+    Error::Io(io::ErrorKind::BrokenPipe.into())
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+mod tests {
+
+    use std::io;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+
+    use crate::port::{PortReader, PortWriter};
+
+    use super::PortSender;
+
+    // each flush waits for a token
+    struct Gate(Receiver<()>, Vec<u8>);
+
+    impl io::Write for Gate {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.recv().map_err(|_| io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    #[test]
+    fn send() {
+        let (sender, handle) = PortSender::spawn(PortWriter::new(Vec::new()).packet(4), 4);
+        let threads = (0..8).map(|i| {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                for j in 0..100 {
+                    sender.send_term(&(i, j, "x".repeat(j))).unwrap();
+                }
+            })
+        }).collect::<Vec<_>>();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(4, sender.capacity());
+        drop(sender);
+
+        let output = handle.join().unwrap().unwrap().into_inner();
+        let mut reader = PortReader::new(output.as_slice()).packet(4);
+        let mut next = [0; 8];
+        for _ in 0..800 {
+            let (i, j, s): (usize, usize, String) = reader.recv_term().unwrap();
+            assert_eq!((next[i], j), (j, s.len()));
+            next[i] += 1;
+        }
+        assert_eq!(None, reader.recv_opt().unwrap());
+    }
+
+    #[test]
+    fn try_send() {
+        let (tx, rx) = mpsc::channel();
+        let (sender, handle) = PortSender::spawn(PortWriter::new(Gate(rx, Vec::new())), 1);
+        sender.send(&[0x61, 0x01]).unwrap();
+        while sender.depth() > 0 {
+            thread::yield_now();
+        }
+        sender.try_send(&[0x61, 0x02]).unwrap();
+        assert_eq!(io::ErrorKind::WouldBlock, match sender.try_send_term(&3) {
+            Err(crate::Error::Io(e)) => e.kind(),
+            r => panic!("{:?}", r),
+        });
+        assert_eq!((1, 1, 0), (sender.depth(), sender.max_depth(), sender.sent()));
+
+        tx.send(()).unwrap();
+        tx.send(()).unwrap();
+        drop(sender);
+        let writer = handle.join().unwrap().unwrap();
+        assert_eq!(vec![0x00, 0x03, 0x83, 0x61, 0x01, 0x00, 0x03, 0x83, 0x61, 0x02], writer.into_inner().1);
+    }
+
+    #[test]
+    fn closed() {
+        let (tx, rx) = mpsc::channel::<()>();
+        drop(tx);
+        let (sender, handle) = PortSender::spawn(PortWriter::new(Gate(rx, Vec::new())), 1);
+        sender.send(&[0x61, 0x01]).unwrap();
+        assert!(handle.join().unwrap().is_err());
+        assert!(sender.send(&[0x61, 0x02]).is_err());
+        assert!(sender.try_send(&[0x61, 0x02]).is_err());
+        assert_eq!(0, sender.depth());
+
+        // frames queued behind the one that failed
+        let (tx, rx) = mpsc::channel::<()>();
+        let (sender, handle) = PortSender::spawn(PortWriter::new(Gate(rx, Vec::new())), 3);
+        for u in 1..=4 {
+            sender.send(&[0x61, u]).unwrap();
+        }
+        assert!(sender.depth() >= 3);
+        drop(tx);
+        assert!(handle.join().unwrap().is_err());
+        assert_eq!(0, sender.depth());
+        assert_eq!(0, sender.sent());
+
+        // a sender blocked on the full queue when the writer fails
+        let (tx, rx) = mpsc::channel::<()>();
+        let (sender, handle) = PortSender::spawn(PortWriter::new(Gate(rx, Vec::new())), 1);
+        sender.send(&[0x61, 0x01]).unwrap();
+        while sender.depth() > 0 {
+            thread::yield_now();
+        }
+        sender.send(&[0x61, 0x02]).unwrap();
+        let blocked = {
+            let sender = sender.clone();
+            thread::spawn(move || sender.send(&[0x61, 0x03]))
+        };
+        while sender.depth() < 2 {
+            thread::yield_now();
+        }
+        drop(tx);
+        assert!(handle.join().unwrap().is_err());
+        assert!(blocked.join().unwrap().is_err());
+        assert_eq!(0, sender.depth());
+    }
+}
@@ -0,0 +1,468 @@
+use std::any::Any;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, RecvError, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+
+use serde::{de, ser};
+
+use crate::atom::InternedAtom;
+use crate::binary::Binary;
+use crate::consts::ERL_VERSION_MAGIC;
+use crate::de::from_slice;
+use crate::dec::Decoder;
+use crate::error::Error;
+use crate::raw::RAW_TERM;
+use crate::ser::to_vec;
+use crate::term::Atom;
+
+use super::{PortReader, PortWriter};
+
+// what a port does with a request, called from any of the workers
+pub trait Handler: Send + Sync {
+    type Request: de::DeserializeOwned;
+    type Response: ser::Serialize;
+    type Error: ser::Serialize;
+
+    fn handle(&self, req: Self::Request) -> Result<Self::Response, Self::Error>;
+}
+
+// reads requests on the calling thread, handles them on workers and writes each reply,
+//  Response or {error, Error}, from a single thread. By default one worker answers each
+//  request in turn, so the n-th reply is to the n-th request. With envelope(true),
+//  {call, From, Request} is answered with {reply, From, Reply} as soon as it is ready and
+//  {cast, Request} is not answered at all; From is passed back untouched so replies find
+//  their caller in any order, and the pool of workers is used. A request that does not decode is answered with
+//  {error, {badarg, Detail}}, a handler that panics with {error, {panic, Msg}}; bytes
+//  that are not a term at all are only logged
+pub struct Server<H> {
+    handler: H,
+    workers: usize,
+    queue: usize,
+    packet: usize,
+    envelope: bool,
+    log: fn(&str),
+}
+
+impl<H> Server<H>
+where
+    H: Handler,
+{
+    pub fn new(handler: H) -> Self {
+        Server {
+            handler,
+            workers: 4,
+            queue: 64,
+            packet: 2,
+            envelope: false,
+            log: |s| eprintln!("{}", s),
+        }
+    }
+
+    // 4 by default; without envelopes there is nothing to match a reply to its request
+    //  but their order, so only one works whatever is asked for
+    pub fn workers(mut self, n: usize) -> Self {
+        self.workers = n.max(1);
+        self
+    }
+
+    // requests read ahead of the workers, and replies waiting for the writer
+    pub fn queue(mut self, n: usize) -> Self {
+        self.queue = n;
+        self
+    }
+
+    // {packet, N} on stdin and stdout
+    pub fn packet(mut self, n: usize) -> Self {
+        self.packet = n;
+        self
+    }
+
+    // {call, From, Request} and {cast, Request} taken apart rather than handed to the
+    //  Handler as they are: those shapes are then reserved, and replies may go out of order
+    pub fn envelope(mut self, b: bool) -> Self {
+        self.envelope = b;
+        self
+    }
+
+    // stderr by default
+    pub fn log(mut self, f: fn(&str)) -> Self {
+        self.log = f;
+        self
+    }
+
+    // until stdin closes
+    pub fn serve(&self) -> Result<(), Error> {
+        let reader = PortReader::new(io::stdin()).packet(self.packet);
+        let writer = PortWriter::new(io::stdout()).packet(self.packet);
+        self.serve_on(reader, writer)
+    }
+
+    // until the reader runs out: the queued requests are still handled and
+    //  their replies written before this returns
+    pub fn serve_on<R, W>(
+        &self,
+        mut reader: PortReader<R>,
+        writer: PortWriter<W>,
+    ) -> Result<(), Error>
+    where
+        R: io::Read,
+        W: io::Write + Send,
+    {
+        let (req_tx, req_rx) = mpsc::sync_channel::<Vec<u8>>(self.queue);
+        let (res_tx, res_rx) = mpsc::sync_channel::<Vec<u8>>(self.queue);
+        let req_rx = Mutex::new(req_rx);
+        let workers = if self.envelope { self.workers } else { 1 };
+        thread::scope(|s| {
+            let w = s.spawn(move || write(writer, res_rx));
+            for _ in 0..workers {
+                let (req_rx, res_tx) = (&req_rx, res_tx.clone());
+                s.spawn(move || {
+                    while let Ok(v) = recv(req_rx) {
+                        if let Some(v) = self.reply(&v) {
+                            // ignored once the writer has failed, the queue is still drained
+                            let _ = res_tx.send(v);
+                        }
+                    }
+                });
+            }
+            drop(res_tx);
+            let r = read(&mut reader, req_tx);
+            let w = w.join().unwrap_or_else(|e| panic::resume_unwind(e));
+            r.and(w)
+        })
+    }
+
+    // v as it was framed, the version magic included
+    fn reply(&self, v: &[u8]) -> Option<Vec<u8>> {
+        let r = match envelope(v, self.envelope) {
+            Some(Envelope::Call(from, v)) => {
+                let res = self.handle(v);
+                to_vec(&(Atom::from("reply"), Raw(from), Raw(&res)))
+            }
+            Some(Envelope::Cast(v)) => {
+                self.handle(v);
+                return None;
+            }
+            Some(Envelope::Plain(v)) => Ok(self.handle(v)),
+            None => {
+                (self.log)(&format!("ei: undecodable request: {:?}", v));
+                return None;
+            }
+        };
+        r.map_err(|e| (self.log)(&format!("ei: reply: {}", e))).ok()
+    }
+
+    // the reply to a request, whatever becomes of it
+    fn handle(&self, v: &[u8]) -> Vec<u8> {
+        let r = panic::catch_unwind(AssertUnwindSafe(|| {
+            let req = match from_slice::<H::Request>(v, false) {
+                Ok(req) => req,
+                Err(e) => return error("badarg", &e.to_string()),
+            };
+            let r = match self.handler.handle(req) {
+                Ok(res) => to_vec(&res),
+                Err(e) => to_vec(&(InternedAtom::ERROR, e)),
+            };
+            r.unwrap_or_else(|e| error("badreply", &e.to_string()))
+        }));
+        r.unwrap_or_else(|e| error("panic", &message(&*e)))
+    }
+}
+
+// {error, {Reason, <<Detail>>}}
+fn error(reason: &str, detail: &str) -> Vec<u8> {
+    let reason = (Atom::from(reason), Binary(detail.as_bytes()));
+    to_vec(&(InternedAtom::ERROR, reason)).expect("error")
+}
+
+fn message(e: &(dyn Any + Send)) -> String {
+    match (e.downcast_ref::<&str>(), e.downcast_ref::<String>()) {
+        (Some(s), _) => s.to_string(),
+        (_, Some(s)) => s.clone(),
+        _ => "Box<dyn Any>".to_string(),
+    }
+}
+
+enum Envelope<'a> {
+    Call(&'a [u8], &'a [u8]),
+    Cast(&'a [u8]),
+    Plain(&'a [u8]),
+}
+
+// None unless v is the version magic and exactly one term; Plain whenever calls is false
+fn envelope(v: &[u8], calls: bool) -> Option<Envelope<'_>> {
+    let v = match v.split_first() {
+        Some((&ERL_VERSION_MAGIC, v)) => v,
+        _ => return None,
+    };
+    let mut dec = Decoder::new(v);
+    if dec.skip_term().is_err() || !dec.remaining().is_empty() {
+        return None;
+    }
+    if !calls {
+        return Some(Envelope::Plain(v));
+    }
+    dec.set_position(0);
+    match (dec.decode_tuple_header(), dec.decode_atom()) {
+        (Ok(3), Ok(a)) if a.as_str() == "call" => {
+            let from = dec.position();
+            dec.skip_term().ok()?;
+            Some(Envelope::Call(&v[from..dec.position()], dec.remaining()))
+        }
+        (Ok(2), Ok(a)) if a.as_str() == "cast" => Some(Envelope::Cast(dec.remaining())),
+        _ => Some(Envelope::Plain(v)),
+    }
+}
+
+// an encoded term, written as it is
+struct Raw<'a>(&'a [u8]);
+
+impl ser::Serialize for Raw<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(RAW_TERM, &Binary(self.0))
+    }
+}
+
+fn read<R>(reader: &mut PortReader<R>, tx: SyncSender<Vec<u8>>) -> Result<(), Error>
+where
+    R: io::Read,
+{
+    while let Some(v) = reader.recv_packet()? {
+        if tx.send(v).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn recv(rx: &Mutex<Receiver<Vec<u8>>>) -> Result<Vec<u8>, RecvError> {
+    rx.lock().unwrap_or_else(|e| e.into_inner()).recv()
+}
+
+fn write<W>(mut writer: PortWriter<W>, rx: Receiver<Vec<u8>>) -> Result<(), Error>
+where
+    W: io::Write,
+{
+    for v in rx {
+        writer.send(&v)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+mod tests {
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::port::{PortReader, PortWriter};
+
+    use super::{Handler, Server};
+
+    struct Calc;
+
+    impl Handler for Calc {
+        type Request = (u8, i64, i64);
+        type Response = i64;
+        type Error = crate::Atom;
+
+        fn handle(&self, req: Self::Request) -> Result<Self::Response, Self::Error> {
+            match req {
+                (b'a', a, b) => Ok(a + b),
+                (b's', a, b) => Ok(a - b),
+                (b'p', _, _) => panic!("p"),
+                _ => Err(crate::atom!("undef")),
+            }
+        }
+    }
+
+    #[test]
+    fn serve() {
+        let mut input = PortWriter::new(Vec::new());
+        input.send_term(&(b'a', 1, 2)).unwrap();
+        input.send_term(&(b's', 1, 2)).unwrap();
+        input.send_term(&(b'm', 1, 2)).unwrap();
+        input.send_term(&"").unwrap();
+        let input = input.into_inner();
+
+        let mut output = Vec::new();
+        Server::new(Calc).workers(1).serve_on(PortReader::new(input.as_slice()), PortWriter::new(&mut output)).unwrap();
+        assert_eq!(
+            vec![
+                0x00, 0x03, 0x83, 0x61, 0x03,
+                0x00, 0x06, 0x83, 0x62, 0xff, 0xff, 0xff, 0xff,
+                0x00, 0x11, 0x83, 0x68, 0x02, 0x77, 0x05, 0x65, 0x72, 0x72, 0x6f, 0x72, 0x77, 0x05, 0x75, 0x6e, 0x64, 0x65, 0x66,
+            ],
+            &output[..32]
+        );
+        let (error, (reason, _)): (crate::Atom, (crate::Atom, crate::Binary)) = PortReader::new(&output[32..]).recv_term().unwrap();
+        assert_eq!((crate::atom!("error"), crate::atom!("badarg")), (error, reason));
+    }
+
+    #[test]
+    fn serve_panic() {
+        let mut input = PortWriter::new(Vec::new());
+        input.send_term(&(b'p', 1, 2)).unwrap();
+        input.send_term(&(b'a', 1, 2)).unwrap();
+        let input = input.into_inner();
+
+        let mut output = Vec::new();
+        Server::new(Calc).workers(1).serve_on(PortReader::new(input.as_slice()), PortWriter::new(&mut output)).unwrap();
+        assert_eq!(
+            vec![
+                0x00, 0x19, 0x83,
+                0x68, 0x02,
+                0x77, 0x05, 0x65, 0x72, 0x72, 0x6f, 0x72,
+                0x68, 0x02, 0x77, 0x05, 0x70, 0x61, 0x6e, 0x69, 0x63, 0x6d, 0x00, 0x00, 0x00, 0x01, 0x70,
+                0x00, 0x03, 0x83, 0x61, 0x03,
+            ],
+            output
+        );
+    }
+
+    #[test]
+    fn serve_undecodable() {
+        let input = vec![
+            0x00, 0x04, 0x83, 0x61, 0x03, 0x61,
+            0x00, 0x02, 0x83, 0x61,
+            0x00, 0x01, 0x83,
+            0x00, 0x03, 0x84, 0x61, 0x03,
+            0x00, 0x09, 0x83, 0x68, 0x03, 0x61, 0x61, 0x61, 0x01, 0x61, 0x02,
+        ];
+        static LOGGED: AtomicUsize = AtomicUsize::new(0);
+        let mut output = Vec::new();
+        Server::new(Calc).log(|_| { LOGGED.fetch_add(1, Ordering::SeqCst); })
+            .serve_on(PortReader::new(input.as_slice()), PortWriter::new(&mut output)).unwrap();
+        assert_eq!(4, LOGGED.load(Ordering::SeqCst));
+        assert_eq!(vec![0x00, 0x03, 0x83, 0x61, 0x03], output);
+    }
+
+    #[test]
+    fn serve_many() {
+        let mut input = PortWriter::new(Vec::new()).packet(4);
+        for i in 0..100 {
+            input.send_term(&(b'a', i, 1)).unwrap();
+        }
+        let mut input = input.into_inner();
+        input.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0xff]); // an empty packet ends the input
+
+        let mut output = Vec::new();
+        Server::new(Calc).workers(8).queue(2)
+            .serve_on(PortReader::new(input.as_slice()).packet(4), PortWriter::new(&mut output).packet(4))
+            .unwrap();
+        let mut reader = PortReader::new(output.as_slice()).packet(4);
+        let actual = (0..100).map(|_| reader.recv_term::<i64>().unwrap()).collect::<Vec<_>>();
+        assert_eq!((1..101).collect::<Vec<_>>(), actual);
+        assert_eq!(None, reader.recv_opt().unwrap());
+    }
+
+    // sleeps for as many milliseconds as asked, panicking at 0
+    struct Slow;
+
+    impl Handler for Slow {
+        type Request = u64;
+        type Response = u64;
+        type Error = ();
+
+        fn handle(&self, req: Self::Request) -> Result<Self::Response, Self::Error> {
+            assert!(req > 0, "zero");
+            std::thread::sleep(std::time::Duration::from_millis(req));
+            Ok(req)
+        }
+    }
+
+    #[test]
+    fn serve_ordered() {
+        // without envelopes replies keep to the order of the requests
+        let mut input = PortWriter::new(Vec::new());
+        for i in &[40u64, 20, 1] {
+            input.send_term(i).unwrap();
+        }
+        let input = input.into_inner();
+
+        let mut output = Vec::new();
+        Server::new(Slow).workers(4).serve_on(PortReader::new(input.as_slice()), PortWriter::new(&mut output)).unwrap();
+        let mut reader = PortReader::new(output.as_slice());
+        assert_eq!(40, reader.recv_term::<u64>().unwrap());
+        assert_eq!(20, reader.recv_term::<u64>().unwrap());
+        assert_eq!(1, reader.recv_term::<u64>().unwrap());
+        assert_eq!(None, reader.recv_opt().unwrap());
+    }
+
+    #[test]
+    fn serve_panic_ordered() {
+        // a panic under the default configuration answers its own request, in its turn,
+        //  even while a slower one ahead of it is still being handled
+        let mut input = PortWriter::new(Vec::new());
+        for i in &[40u64, 0, 1] {
+            input.send_term(i).unwrap();
+        }
+        let input = input.into_inner();
+
+        let mut output = Vec::new();
+        Server::new(Slow).serve_on(PortReader::new(input.as_slice()), PortWriter::new(&mut output)).unwrap();
+        let mut reader = PortReader::new(output.as_slice());
+        assert_eq!(40, reader.recv_term::<u64>().unwrap());
+        let (error, (reason, message)): (crate::Atom, (crate::Atom, crate::Binary)) = reader.recv_term().unwrap();
+        assert_eq!((crate::atom!("error"), crate::atom!("panic"), b"zero".to_vec()), (error, reason, message.0));
+        assert_eq!(1, reader.recv_term::<u64>().unwrap());
+        assert_eq!(None, reader.recv_opt().unwrap());
+    }
+
+    #[test]
+    fn serve_call() {
+        let mut input = PortWriter::new(Vec::new());
+        for i in 0..100u8 {
+            // {call, {i, [alias | ref]}, {$a, i, 1}}
+            let from = crate::RawTerm::from_vec(vec![0x68, 0x02, 0x61, i, 0x6c, 0x00, 0x00, 0x00, 0x01, 0x61, 0x00, 0x61, 0x00]).unwrap();
+            input.send_term(&(crate::atom!("call"), from, (b'a', i, 1))).unwrap();
+            input.send_term(&(crate::atom!("cast"), (b'a', i, 1))).unwrap();
+        }
+        // {cast, bad}
+        input.send_term(&(crate::atom!("cast"), crate::atom!("bad"))).unwrap();
+        let input = input.into_inner();
+
+        let mut output = Vec::new();
+        Server::new(Calc).workers(8).envelope(true).serve_on(PortReader::new(input.as_slice()), PortWriter::new(&mut output)).unwrap();
+        let mut reader = PortReader::new(output.as_slice());
+        for _ in 0..100 {
+            let (reply, (i, _), res): (crate::Atom, (u8, crate::RawTerm), i64) = reader.recv_term().unwrap();
+            assert_eq!(crate::atom!("reply"), reply);
+            assert_eq!(i as i64 + 1, res);
+        }
+        assert_eq!(None, reader.recv_opt().unwrap());
+
+        // {call, From, bad}
+        let input = vec![
+            0x00, 0x10, 0x83,
+            0x68, 0x03,
+            0x77, 0x04, 0x63, 0x61, 0x6c, 0x6c,
+            0x61, 0x07,
+            0x77, 0x03, 0x62, 0x61, 0x64,
+        ];
+        let mut output = Vec::new();
+        Server::new(Calc).envelope(true).serve_on(PortReader::new(input.as_slice()), PortWriter::new(&mut output)).unwrap();
+        let (reply, from, (error, (reason, _))): (crate::Atom, u8, (crate::Atom, (crate::Atom, crate::Binary))) =
+            PortReader::new(output.as_slice()).recv_term().unwrap();
+        assert_eq!((crate::atom!("reply"), 7, crate::atom!("error"), crate::atom!("badarg")), (reply, from, error, reason));
+
+        // handed to the Handler as it is unless asked otherwise
+        let mut output = Vec::new();
+        Server::new(Calc).serve_on(PortReader::new(input.as_slice()), PortWriter::new(&mut output)).unwrap();
+        let (error, (reason, _)): (crate::Atom, (crate::Atom, crate::Binary)) = PortReader::new(output.as_slice()).recv_term().unwrap();
+        assert_eq!((crate::atom!("error"), crate::atom!("badarg")), (error, reason));
+    }
+
+    #[test]
+    fn serve_error() {
+        let input = vec![0x00, 0x03, 0x83, 0x61];
+        let mut output = Vec::new();
+        assert!(Server::new(Calc).serve_on(PortReader::new(input.as_slice()), PortWriter::new(&mut output)).is_err());
+        assert!(output.is_empty());
+    }
+}
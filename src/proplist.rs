@@ -0,0 +1,188 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::{de, ser};
+
+pub const PROPLIST: &str = "$ei::Proplist";
+
+// [{Key, Value}, ...], a struct or map written as a proplist; None fields are left out.
+//  Decoded as proplists:get_value/2 sees it: a bare atom is true, the first key wins
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Proplist<T>(pub T);
+
+impl<T> ser::Serialize for Proplist<T>
+where
+    T: ser::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(PROPLIST, &self.0)
+    }
+}
+
+struct ProplistVisitor<T>(PhantomData<T>);
+
+impl<'de, T> de::Visitor<'de> for ProplistVisitor<T>
+where
+    T: de::Deserialize<'de>,
+{
+    type Value = Proplist<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a proplist")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Proplist)
+    }
+}
+
+impl<'de, T> de::Deserialize<'de> for Proplist<T>
+where
+    T: de::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(PROPLIST, ProplistVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+mod tests {
+
+    use std::collections::{BTreeMap, HashMap};
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_reader, to_vec, to_vec_deterministic, Deserializer};
+
+    use super::Proplist;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+    #[serde(rename = "opts")]
+    struct Opts {
+        timeout: u32,
+        #[serde(default)]
+        verbose: bool,
+        mode: Option<String>,
+    }
+
+    #[test]
+    fn deserialize() {
+        // [{timeout, 5000}, verbose, {mode, "fast"}, {other, 1}, {timeout, 1}]
+        let input = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x05,
+            0x68, 0x02, 0x77, 0x07, 0x74, 0x69, 0x6d, 0x65, 0x6f, 0x75, 0x74,
+                        0x62, 0x00, 0x00, 0x13, 0x88,
+            0x64, 0x00, 0x07, 0x76, 0x65, 0x72, 0x62, 0x6f, 0x73, 0x65,
+            0x68, 0x02, 0x77, 0x04, 0x6d, 0x6f, 0x64, 0x65,
+                        0x6b, 0x00, 0x04, 0x66, 0x61, 0x73, 0x74,
+            0x68, 0x02, 0x77, 0x05, 0x6f, 0x74, 0x68, 0x65, 0x72, 0x61, 0x01,
+            0x68, 0x02, 0x77, 0x07, 0x74, 0x69, 0x6d, 0x65, 0x6f, 0x75, 0x74, 0x61, 0x01,
+            0x6a,
+        ];
+        let expected = Opts { timeout: 5000, verbose: true, mode: Some("fast".to_string()) };
+        assert_eq!(expected, from_reader::<_, Proplist<Opts>>(&input[..]).unwrap().0);
+        assert!(from_reader::<_, Opts>(&input[..]).is_err());
+
+        // [{timeout, 1}]
+        let input = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x01,
+            0x68, 0x02, 0x77, 0x07, 0x74, 0x69, 0x6d, 0x65, 0x6f, 0x75, 0x74, 0x61, 0x01,
+            0x6a,
+        ];
+        let expected = Opts { timeout: 1, ..Default::default() };
+        assert_eq!(expected, from_reader::<_, Proplist<Opts>>(&input[..]).unwrap().0);
+
+        assert!(from_reader::<_, Proplist<Opts>>(&[0x6a][..]).is_err());
+        assert!(from_reader::<_, Proplist<Opts>>(&[0x6c, 0x00, 0x00, 0x00, 0x01, 0x61, 0x01, 0x6a][..]).is_err());
+        assert!(from_reader::<_, Proplist<Opts>>(&[0x6c, 0x00, 0x00, 0x00, 0x01, 0x68, 0x01, 0x77, 0x01, 0x61, 0x6a][..]).is_err());
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(rename = "outer")]
+    struct Outer {
+        inner: Inner,
+        list: Vec<Option<u8>>,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(rename = "inner")]
+    struct Inner {
+        a: Option<u8>,
+    }
+
+    #[test]
+    fn nested() {
+        // [{inner, #{a => nil}}, {list, [nil, 1]}], nil being None to the elixir profile
+        let input = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x02,
+            0x68, 0x02, 0x77, 0x05, 0x69, 0x6e, 0x6e, 0x65, 0x72,
+                        0x74, 0x00, 0x00, 0x00, 0x01, 0x77, 0x01, 0x61, 0x77, 0x03, 0x6e, 0x69, 0x6c,
+            0x68, 0x02, 0x77, 0x04, 0x6c, 0x69, 0x73, 0x74,
+                        0x6c, 0x00, 0x00, 0x00, 0x02, 0x77, 0x03, 0x6e, 0x69, 0x6c, 0x61, 0x01, 0x6a,
+            0x6a,
+        ];
+        let actual: Proplist<Outer> = Proplist::deserialize(&mut Deserializer::new(&input[..]).elixir(true)).unwrap();
+        assert_eq!(Outer { inner: Inner { a: None }, list: vec![None, Some(1)] }, actual.0);
+    }
+
+    #[test]
+    fn serialize() {
+        let input = Proplist(Opts { timeout: 1, verbose: true, mode: None });
+        let expected = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x02,
+            0x68, 0x02, 0x77, 0x07, 0x74, 0x69, 0x6d, 0x65, 0x6f, 0x75, 0x74, 0x61, 0x01,
+            0x68, 0x02, 0x77, 0x07, 0x76, 0x65, 0x72, 0x62, 0x6f, 0x73, 0x65,
+                        0x77, 0x04, 0x74, 0x72, 0x75, 0x65,
+            0x6a,
+        ];
+        assert_eq!(expected, to_vec(&input).unwrap());
+        assert_eq!(input, from_reader(&expected[..]).unwrap());
+        assert!(to_vec(&Proplist(1u8)).is_err());
+    }
+
+    #[test]
+    fn map() {
+        let input: BTreeMap<String, u8> = vec![("b".to_string(), 2), ("a".to_string(), 1)].into_iter().collect();
+        let expected = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x02,
+            0x68, 0x02, 0x77, 0x01, 0x61, 0x61, 0x01,
+            0x68, 0x02, 0x77, 0x01, 0x62, 0x61, 0x02,
+            0x6a,
+        ];
+        assert_eq!(expected, to_vec(&Proplist(&input)).unwrap());
+        let actual: Proplist<BTreeMap<String, u8>> = from_reader(to_vec(&Proplist(&input)).unwrap().as_slice()).unwrap();
+        assert_eq!(input, actual.0);
+        assert_eq!(vec![0x6a], to_vec(&Proplist(BTreeMap::<String, u8>::new())).unwrap());
+
+        let input: HashMap<u8, u8> = vec![(3, 0), (1, 0), (2, 0)].into_iter().collect();
+        let expected = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x03,
+            0x68, 0x02, 0x61, 0x01, 0x61, 0x00,
+            0x68, 0x02, 0x61, 0x02, 0x61, 0x00,
+            0x68, 0x02, 0x61, 0x03, 0x61, 0x00,
+            0x6a,
+        ];
+        assert_eq!(expected, to_vec_deterministic(&Proplist(&input)).unwrap());
+
+        // [{a, false}, b]
+        let input = vec![
+            0x6c, 0x00, 0x00, 0x00, 0x02,
+            0x68, 0x02, 0x77, 0x01, 0x61, 0x77, 0x05, 0x66, 0x61, 0x6c, 0x73, 0x65,
+            0x77, 0x01, 0x62,
+            0x6a,
+        ];
+        let actual: Proplist<HashMap<String, bool>> = from_reader(&input[..]).unwrap();
+        let expected: HashMap<String, bool> = vec![("a".to_string(), false), ("b".to_string(), true)].into_iter().collect();
+        assert_eq!(expected, actual.0);
+    }
+}
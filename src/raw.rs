@@ -0,0 +1,124 @@
+use std::fmt;
+
+use serde::{de, ser};
+
+use crate::binary::Binary;
+use crate::dec::Decoder;
+use crate::error::Error;
+
+pub const RAW_TERM: &str = "$ei::RawTerm";
+
+// one encoded term, passed through without decoding
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct RawTerm(Vec<u8>);
+
+impl RawTerm {
+    pub fn from_vec(vec: Vec<u8>) -> Result<Self, Error> {
+        let mut dec = Decoder::new(&vec);
+        dec.skip_term()?;
+        match dec.position() {
+            u if u == vec.len() => Ok(RawTerm(vec)),
+            u => Err(invalid_input!("from_vec: {}, {}", u, vec.len())),
+        }
+    }
+
+    pub fn from_value<T>(value: &T) -> Result<Self, Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        crate::to_vec(value).map(RawTerm)
+    }
+
+    pub fn to_value<T>(&self) -> Result<T, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        crate::from_reader(self.0.as_slice())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl ser::Serialize for RawTerm {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(RAW_TERM, &Binary(&self.0))
+    }
+}
+
+struct RawTermVisitor;
+
+impl<'de> de::Visitor<'de> for RawTermVisitor {
+    type Value = RawTerm;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an encoded term")
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RawTerm(v))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for RawTerm {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(RAW_TERM, RawTermVisitor)
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+mod tests {
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::term;
+    use crate::{from_reader, to_vec};
+
+    use super::RawTerm;
+
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct Call(term::Atom, u8, RawTerm);
+
+    #[test]
+    fn round_trip() {
+        let payload = (vec![1u8, 2, 3], term::Pid {
+            node: term::Atom::UTF8("n".to_owned()),
+            num: 1,
+            serial: 2,
+            creation: 3,
+        });
+        let raw = RawTerm::from_value(&payload).unwrap();
+        let input = Call(term::Atom::UTF8Small("call".to_owned()), 7, raw.clone());
+
+        let vec = to_vec(&input).unwrap();
+        assert!(vec.ends_with(raw.as_bytes()));
+
+        let actual: Call = from_reader(vec.as_slice()).unwrap();
+        assert_eq!(actual, input);
+        assert_eq!(actual.2.as_bytes(), to_vec(&payload).unwrap().as_slice());
+        assert_eq!(actual.2.to_value::<(Vec<u8>, term::Pid)>().unwrap(), payload);
+    }
+
+    #[test]
+    fn from_vec() {
+        assert!(RawTerm::from_vec(vec![0x61, 0x01]).is_ok());
+        assert!(RawTerm::from_vec(vec![0x61, 0x01, 0x6a]).is_err());
+        assert!(RawTerm::from_vec(vec![0x68, 0x02, 0x6a]).is_err());
+        assert!(RawTerm::from_vec(vec![]).is_err());
+    }
+}
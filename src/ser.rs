@@ -2,15 +2,24 @@ use std::io;
 
 use serde::{ser, serde_if_integer128};
 
+use crate::binary::CHARLIST;
 use crate::consts::*;
+use crate::elixir::ELIXIR_STRUCT;
 use crate::error::Error;
 use crate::i27;
 use crate::io::Writer;
+use crate::order;
+use crate::proplist::PROPLIST;
+use crate::raw::RAW_TERM;
+use crate::term::AtomStr;
+use crate::value;
 
 pub struct Serializer<W> {
     writer: Writer<W>,
     etype: Vec<[u8; 2]>,
     ref_n: Option<usize>,
+    deterministic: bool,
+    elixir: bool,
 }
 
 impl<W> Serializer<W>
@@ -22,40 +31,114 @@ where
             writer: Writer::new(w),
             etype: Vec::with_capacity(16),
             ref_n: None,
+            deterministic: false,
+            elixir: false,
         }
     }
 
+    // term_to_binary(T, [deterministic]): map entries are written in term order
+    pub fn deterministic(mut self, b: bool) -> Self {
+        self.deterministic = b;
+        self
+    }
+
+    // elixir conventions: strings as binaries, nil for None, structs as %Module{}
+    pub fn elixir(mut self, b: bool) -> Self {
+        self.elixir = b;
+        self
+    }
+
+    fn fork(&self) -> Serializer<Vec<u8>> {
+        Serializer::new(Vec::new())
+            .deterministic(self.deterministic)
+            .elixir(self.elixir)
+    }
+
+    fn write_struct(&mut self, name: &str, len: usize) -> Result<Compound<'_, W>, Error> {
+        let module = match name.starts_with("Elixir.") {
+            true => name.to_owned(),
+            false => format!("Elixir.{}", name),
+        };
+        let map = if self.deterministic {
+            let (mut k, mut v) = (self.fork(), self.fork());
+            k.writer.write_atom_utf8("__struct__")?;
+            v.writer.write_atom_utf8(&module)?;
+            let mut vec = Vec::with_capacity(len + 1);
+            vec.push((k.writer.into_inner(), v.writer.into_inner()));
+            Some(vec)
+        } else {
+            self.writer.write_u8(ERL_MAP_EXT)?;
+            self.writer.write_u32(len as u32 + 1)?;
+            self.writer.write_atom_utf8("__struct__")?;
+            self.writer.write_atom_utf8(&module)?;
+            None
+        };
+        Ok(Compound {
+            ser: self,
+            nil: false,
+            map,
+            keys: true,
+            prop: false,
+        })
+    }
+
+    fn write_map(&mut self, vec: Entries) -> Result<(), Error> {
+        self.writer.write_u8(ERL_MAP_EXT)?;
+        self.writer.write_u32(vec.len() as u32)?;
+        for (k, v) in sort(vec)? {
+            self.writer.write_all(&k)?;
+            self.writer.write_all(&v)?;
+        }
+        Ok(())
+    }
+
+    // [{K, V}, ...], in term order of the keys when deterministic
+    fn write_proplist(&mut self, vec: Entries) -> Result<(), Error> {
+        let vec = if self.deterministic { sort(vec)? } else { vec };
+        if !vec.is_empty() {
+            self.writer.write_u8(ERL_LIST_EXT)?;
+            self.writer.write_u32(vec.len() as u32)?;
+        }
+        for (k, v) in vec {
+            self.writer.write_tuple(2)?;
+            self.writer.write_all(&k)?;
+            self.writer.write_all(&v)?;
+        }
+        self.writer.write_u8(ERL_NIL_EXT)
+    }
+
     pub fn write_i27(&mut self, v: i32) -> Result<(), Error> {
-        self.writer.write_u8(ERL_INTEGER_EXT)?;
-        self.writer.write_i32(v)
+        self.writer.write_i27(v)
     }
 
     pub fn write_small_big(&mut self, s: u8, v: u64) -> Result<(), Error> {
-        let mut vec = Vec::with_capacity(8);
-        let mut u = v;
-        while u != 0 {
-            vec.push((u & 0xff) as u8);
-            u >>= 8;
-        }
-        self.writer
-            .write_all(&[ERL_SMALL_BIG_EXT, vec.len() as u8, s])?;
-        self.writer.write_all(vec.as_slice())
+        self.writer.write_small_big(s, v)
     }
 
     pub fn write_tuple(&mut self, len: usize) -> Result<(), Error> {
-        if len > u8::MAX as usize {
-            self.writer.write_u8(ERL_LARGE_TUPLE_EXT)?;
-            self.writer.write_u32(len as u32)
-        } else {
-            self.writer.write_all(&[ERL_SMALL_TUPLE_EXT, len as u8])
-        }
+        self.writer.write_tuple(len)
+    }
+}
+
+type Entries = Vec<(Vec<u8>, Vec<u8>)>;
+
+fn sort(mut vec: Entries) -> Result<Entries, Error> {
+    let mut keys = Vec::with_capacity(vec.len());
+    for (k, _) in &vec {
+        keys.push(value::parse(k)?.0);
     }
+    let mut idx = (0..vec.len()).collect::<Vec<_>>();
+    idx.sort_by(|&a, &b| order::cmp(&keys[a], &keys[b]));
+    Ok(idx
+        .into_iter()
+        .map(|i| std::mem::take(&mut vec[i]))
+        .collect())
 }
 
 #[rustfmt::skip]
 macro_rules! compound {
-    ($s: expr) => { Compound { ser: $s, nil: false } };
-    ($s: expr, $b: expr) => { Compound { ser: $s, nil: $b } };
+    ($s: expr) => { Compound { ser: $s, nil: false, map: None, keys: false, prop: false } };
+    ($s: expr, $b: expr) => { Compound { ser: $s, nil: $b, map: None, keys: false, prop: false } };
 }
 
 impl<'a, W> ser::Serializer for &'a mut Serializer<W>
@@ -110,7 +193,7 @@ where
             i if range!(i,  u8, i32) => self.serialize_u8(i as u8),
             i if range!(i, i27, i32) => self.write_i27(i),
             i if i >= 0              => self.write_small_big(0, i as u64),
-            i if i >  i32::MIN       => self.write_small_big(1, i.abs() as u64),
+            i if i >  i32::MIN       => self.write_small_big(1, i.unsigned_abs() as u64),
             i                        => Err(invalid_input!("serialize_i32: {}", i)),
         }
     }
@@ -121,7 +204,7 @@ where
             i if range!(i,  u8, i64) => self.serialize_u8(i as u8),
             i if range!(i, i27, i64) => self.write_i27(i as i32),
             i if i >= 0              => self.write_small_big(0, i as u64),
-            i if i >  i64::MIN       => self.write_small_big(1, i.abs() as u64),
+            i if i >  i64::MIN       => self.write_small_big(1, i.unsigned_abs()),
             i                        => Err(invalid_input!("serialize_i64: {}", i)),
         }
     }
@@ -199,35 +282,27 @@ where
 
     #[rustfmt::skip]
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        match (self.etype.pop(), v.len()) {
-            (None, u) if u == u16::MIN as usize => {
-                self.serialize_unit()
-            }
-            (None, u) if u <= u16::MAX as usize => {
-                self.writer.write_u8(ERL_STRING_EXT)?;
-                self.writer.write_u16(u as u16)?;
-                self.writer.write_all(v.as_bytes())
-            }
-            (Some([ERL_ATOM_EXT, _]), u) if u <= MAXATOMLEN as usize => {
-                self.writer.write_u8(ERL_ATOM_EXT)?;
-                self.writer.write_u16(u as u16)?;
-                self.writer.write_all(v.as_bytes())
+        match self.etype.pop() {
+            None if self.elixir => {
+                self.serialize_bytes(v.as_bytes())
             }
-            (Some([ERL_SMALL_ATOM_UTF8_EXT, _]), u) if u <= u8::MAX as usize => {
-                self.writer.write_u8(ERL_SMALL_ATOM_UTF8_EXT)?;
-                self.writer.write_u8(u as u8)?;
-                self.writer.write_all(v.as_bytes())
+            None | Some([ERL_STRING_EXT, 0]) => {
+                self.writer.write_charlist(v)
             }
-            (Some([ERL_ATOM_UTF8_EXT, _]), u) if u <= MAXATOMLEN_UTF8 as usize => {
-                self.writer.write_u8(ERL_ATOM_UTF8_EXT)?;
-                self.writer.write_u16(u as u16)?;
-                self.writer.write_all(v.as_bytes())
+            Some([u @ ERL_ATOM_EXT, _]) |
+            Some([u @ ERL_SMALL_ATOM_UTF8_EXT, _]) |
+            Some([u @ ERL_ATOM_UTF8_EXT, _]) => {
+                self.writer.write_atom_ext(u, v)
             }
             o => Err(interrupted!("serialize_str: {:?}", o)),
         }
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        if let Some([ERL_VERSION_MAGIC, 0]) = self.etype.last() {
+            self.etype.pop();
+            return self.writer.write_all(v); // raw term
+        }
         match v.len() {
             u if u <= u32::MAX as usize => {
                 self.writer.write_u8(ERL_BINARY_EXT)?;
@@ -239,7 +314,10 @@ where
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        match self.elixir {
+            true => self.writer.write_atom_utf8("nil"),
+            false => Ok(()),
+        }
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
@@ -268,12 +346,45 @@ where
 
     fn serialize_newtype_struct<T>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ser::Serialize + ?Sized,
     {
+        match name {
+            RAW_TERM => {
+                self.etype.push([ERL_VERSION_MAGIC, 0]);
+                return value.serialize(&mut *self);
+            }
+            CHARLIST => {
+                self.etype.push([ERL_STRING_EXT, 0]);
+                return value.serialize(&mut *self);
+            }
+            ELIXIR_STRUCT => {
+                self.etype.push([ERL_MAP_EXT, 0]);
+                value.serialize(&mut *self)?;
+                return match self.etype.last() {
+                    Some([ERL_MAP_EXT, 0]) => {
+                        self.etype.pop();
+                        Err(invalid_input!("serialize_newtype_struct: {}", name))
+                    }
+                    _ => Ok(()),
+                };
+            }
+            PROPLIST => {
+                self.etype.push([ERL_LIST_EXT, 0]);
+                value.serialize(&mut *self)?;
+                return match self.etype.last() {
+                    Some([ERL_LIST_EXT, 0]) => {
+                        self.etype.pop();
+                        Err(invalid_input!("serialize_newtype_struct: {}", name))
+                    }
+                    _ => Ok(()),
+                };
+            }
+            _ => (),
+        }
         self.writer.write_all(&[ERL_SMALL_TUPLE_EXT, 1])?;
         value.serialize(&mut *self)
     }
@@ -355,6 +466,27 @@ where
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, self::Error> {
         match len {
+            u if self.etype.last() == Some(&[ERL_LIST_EXT, 0]) => {
+                self.etype.pop();
+                let vec = Vec::with_capacity(u.unwrap_or(0));
+                Ok(Compound {
+                    ser: self,
+                    nil: false,
+                    map: Some(vec),
+                    keys: false,
+                    prop: true,
+                })
+            }
+            u if self.deterministic => {
+                let vec = Vec::with_capacity(u.unwrap_or(0));
+                Ok(Compound {
+                    ser: self,
+                    nil: false,
+                    map: Some(vec),
+                    keys: false,
+                    prop: false,
+                })
+            }
             Some(u) => {
                 self.writer.write_u8(ERL_MAP_EXT)?;
                 self.writer.write_u32(u as u32)?;
@@ -384,6 +516,22 @@ where
                 self.etype.push([ERL_NEWER_REFERENCE_EXT, 1]); // len
                 Ok(compound!(self))
             }
+            _ if self.etype.last() == Some(&[ERL_MAP_EXT, 0]) => {
+                self.etype.pop();
+                self.write_struct(name, len)
+            }
+            _ if self.etype.last() == Some(&[ERL_LIST_EXT, 0]) => {
+                self.etype.pop();
+                let vec = Vec::with_capacity(len);
+                Ok(Compound {
+                    ser: self,
+                    nil: false,
+                    map: Some(vec),
+                    keys: true,
+                    prop: true,
+                })
+            }
+            _ if self.elixir => self.write_struct(name, len),
             _ => {
                 self.write_tuple(len)?;
                 Ok(compound!(self))
@@ -477,6 +625,9 @@ where
 pub struct Compound<'a, W> {
     ser: &'a mut Serializer<W>,
     nil: bool,
+    map: Option<Vec<(Vec<u8>, Vec<u8>)>>,
+    keys: bool, // struct fields, keyed by atom
+    prop: bool, // entries as a list of {K, V}
 }
 
 impl<'a, W> ser::SerializeSeq for Compound<'a, W>
@@ -570,18 +721,47 @@ where
     where
         K: ser::Serialize + ?Sized,
     {
-        key.serialize(&mut *self.ser)
+        match self.map {
+            Some(ref mut vec) => {
+                let mut ser = self.ser.fork();
+                match self.prop {
+                    true => key.serialize(PropKey(&mut ser))?,
+                    false => key.serialize(&mut ser)?,
+                }
+                vec.push((ser.writer.into_inner(), Vec::new()));
+                Ok(())
+            }
+            None => key.serialize(&mut *self.ser),
+        }
     }
 
     fn serialize_value<V>(&mut self, value: &V) -> Result<Self::Ok, Self::Error>
     where
         V: ser::Serialize + ?Sized,
     {
-        value.serialize(&mut *self.ser)
+        match self.map.as_mut().and_then(|vec| vec.last_mut()) {
+            Some((_, v)) => {
+                let mut ser = self.ser.fork();
+                value.serialize(&mut ser)?;
+                *v = ser.writer.into_inner();
+                // None leaves nothing to pair the key with, so the entry is omitted
+                if self.prop && v.is_empty() {
+                    if let Some(vec) = self.map.as_mut() {
+                        vec.pop();
+                    }
+                }
+                Ok(())
+            }
+            None => value.serialize(&mut *self.ser),
+        }
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        match self.map {
+            Some(vec) if self.prop => self.ser.write_proplist(vec),
+            Some(vec) => self.ser.write_map(vec),
+            None => Ok(()),
+        }
     }
 }
 
@@ -592,15 +772,21 @@ where
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: ser::Serialize + ?Sized,
     {
-        value.serialize(&mut *self.ser)
+        match self.keys {
+            true => ser::SerializeMap::serialize_entry(self, &AtomStr(key), value),
+            false => value.serialize(&mut *self.ser),
+        }
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        match self.keys {
+            true => ser::SerializeMap::end(self),
+            false => Ok(()),
+        }
     }
 }
 
@@ -623,6 +809,201 @@ where
     }
 }
 
+// a proplist map key: a string is written as an atom, as proplists:get_value/2 looks it
+//  up, anything else as it would be anywhere
+struct PropKey<'a, W>(&'a mut Serializer<W>);
+
+impl<'a, W> ser::Serializer for PropKey<'a, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.0.writer.write_atom_utf8(v)
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_i64(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_char(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_none()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        self.0.serialize_some(value)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        self.0.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        self.0
+            .serialize_newtype_variant(name, variant_index, variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, self::Error> {
+        self.0.serialize_seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, self::Error> {
+        self.0.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, self::Error> {
+        self.0.serialize_tuple_struct(name, len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, self::Error> {
+        self.0
+            .serialize_tuple_variant(name, variant_index, variant, len)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, self::Error> {
+        self.0.serialize_map(len)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, self::Error> {
+        self.0.serialize_struct(name, len)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, self::Error> {
+        self.0
+            .serialize_struct_variant(name, variant_index, variant, len)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+struct Counter(usize);
+
+impl io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 fn to_writer<W, T>(writer: W, value: &T) -> Result<(), Error>
 where
     W: io::Write,
@@ -632,16 +1013,42 @@ where
     value.serialize(&mut ser)
 }
 
-pub fn to_vec<T>(value: &T, capacity: usize) -> Result<Vec<u8>, Error>
+// erlang:external_size/1 - 1, the ERL_VERSION_MAGIC is not counted
+pub fn encoded_size<T>(value: &T) -> Result<usize, Error>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let mut counter = Counter(0);
+    to_writer(&mut counter, value).map(|()| counter.0)
+}
+
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: ser::Serialize + ?Sized,
+{
+    encoded_size(value).and_then(|u| to_vec_with_capacity(value, u))
+}
+
+pub fn to_vec_with_capacity<T>(value: &T, capacity: usize) -> Result<Vec<u8>, Error>
 where
     T: ser::Serialize + ?Sized,
 {
     let mut vec = Vec::with_capacity(capacity);
-    to_writer(&mut vec, value).and_then(|()| Ok(vec))
+    to_writer(&mut vec, value).map(|()| vec)
+}
+
+pub fn to_vec_deterministic<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let mut vec = Vec::with_capacity(encoded_size(value)?);
+    let mut ser = Serializer::new(&mut vec).deterministic(true);
+    value.serialize(&mut ser).map(|()| vec)
 }
 
 #[cfg(test)]
 #[rustfmt::skip]
+#[allow(clippy::identity_op, clippy::unnecessary_cast, clippy::useless_vec)]
 mod tests {
 
     use std::collections::HashMap;
@@ -649,6 +1056,7 @@ mod tests {
 
     use serde::Serialize;
 
+    use crate::consts::MAXATOMLEN;
     use crate::i27;
     use crate::term;
 
@@ -670,10 +1078,33 @@ mod tests {
 
     macro_rules! test {
         ($value: expr) => {
-            super::to_vec(&$value, 80)
+            super::to_vec(&$value)
         };
     }
 
+    #[test]
+    fn encoded_size() {
+        macro_rules! size {
+            ($value: expr) => {
+                assert_eq!(test!(&$value).unwrap().len(), super::encoded_size(&$value).unwrap(), "{:?}", test!(&$value));
+            };
+        }
+        size!(0u8);
+        size!(i64::MAX);
+        size!("hello");
+        size!(vec![1, 2, 3]);
+        size!(Point2D(1, 2));
+        size!(term::Atom::UTF8("n".to_owned()));
+
+        let actual = test!(Color { r: 1, g: 2, b: 3 as f64}).unwrap();
+        assert_eq!(0x0f, super::encoded_size(&Color { r: 1, g: 2, b: 3 as f64}).unwrap());
+        assert_eq!(0x0f, actual.capacity());
+
+        let value: HashMap<&str, u8> = vec![("b", 2), ("a", 1)].into_iter().collect();
+        let actual = super::to_vec_deterministic(&value).unwrap();
+        assert_eq!(actual.len(), actual.capacity());
+    }
+
     #[test]
     fn serialize_bool() {
         for (input, expected) in vec![
@@ -831,6 +1262,8 @@ mod tests {
         for (input, expected) in vec![
             ("",      vec![0x6a]),
             ("hello", vec![0x6b, 0x00, 0x05, 0x68, 0x65, 0x6c, 0x6c, 0x6f]),
+            ("café",  vec![0x6b, 0x00, 0x04, 0x63, 0x61, 0x66, 0xe9]),
+            ("aア",   vec![0x6c, 0x00, 0x00, 0x00, 0x02, 0x61, 0x61, 0x62, 0x00, 0x00, 0x30, 0xa2, 0x6a]),
         ] {
             assert_eq!(expected, test!(&input).unwrap(), "{:?}", input);
         }
@@ -935,6 +1368,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn serialize_map_deterministic() {
+        let mut map = HashMap::new();
+        for i in &[300, 3, -1, 1, 2] {
+            map.insert(*i, 0);
+        }
+        assert_eq!(
+            vec![
+                0x74, 0x00, 0x00, 0x00, 0x05,
+                0x62, 0xff, 0xff, 0xff, 0xff, 0x61, 0x00,
+                0x61, 0x01, 0x61, 0x00,
+                0x61, 0x02, 0x61, 0x00,
+                0x61, 0x03, 0x61, 0x00,
+                0x62, 0x00, 0x00, 0x01, 0x2c, 0x61, 0x00,
+            ],
+            super::to_vec_deterministic(&map).unwrap()
+        );
+
+        let mut map = HashMap::new();
+        for s in &["b", "", "ab", "a"] {
+            map.insert(*s, HashMap::<u8, u8>::new());
+        }
+        assert_eq!(
+            vec![
+                0x74, 0x00, 0x00, 0x00, 0x04,
+                0x6a, 0x74, 0x00, 0x00, 0x00, 0x00,
+                0x6b, 0x00, 0x01, 0x61, 0x74, 0x00, 0x00, 0x00, 0x00,
+                0x6b, 0x00, 0x02, 0x61, 0x62, 0x74, 0x00, 0x00, 0x00, 0x00,
+                0x6b, 0x00, 0x01, 0x62, 0x74, 0x00, 0x00, 0x00, 0x00,
+            ],
+            super::to_vec_deterministic(&map).unwrap()
+        );
+
+        let mut map = HashMap::new();
+        for (k, v) in &[(2u8, 0u8), (1, 1)] {
+            map.insert(*k, [(*v, 0u8), (1 - *v, 1)].iter().cloned().collect::<HashMap<_, _>>());
+        }
+        assert_eq!(
+            vec![
+                0x74, 0x00, 0x00, 0x00, 0x02,
+                0x61, 0x01,
+                  0x74, 0x00, 0x00, 0x00, 0x02, 0x61, 0x00, 0x61, 0x01, 0x61, 0x01, 0x61, 0x00,
+                0x61, 0x02,
+                  0x74, 0x00, 0x00, 0x00, 0x02, 0x61, 0x00, 0x61, 0x00, 0x61, 0x01, 0x61, 0x01,
+            ],
+            super::to_vec_deterministic(&map).unwrap()
+        );
+
+        // keys sharing a long prefix are compared a byte at a time, not recursively
+        let prefix = "a".repeat(u16::MAX as usize - 1);
+        let map: HashMap<String, u8> = vec![(format!("{}b", prefix), 1), (format!("{}a", prefix), 0)].into_iter().collect();
+        let actual = super::to_vec_deterministic(&map).unwrap();
+        assert_eq!(0x61, actual[5 + 3 + u16::MAX as usize - 1]);
+    }
+
     #[test]
     fn serialize_struct() {
         assert_eq!(
@@ -956,6 +1444,10 @@ mod tests {
                 term::Atom::Latin1("n".to_owned()),
                 vec![0x64, 0x00, 0x01, 0x6e],
             ),
+            (
+                term::Atom::Latin1("café".to_owned()),
+                vec![0x64, 0x00, 0x04, 0x63, 0x61, 0x66, 0xe9],
+            ),
             (
                 term::Atom::UTF8Small("n".to_owned()),
                 vec![0x77, 0x01, 0x6e],
@@ -969,6 +1461,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn serialize_atom_latin1() {
+        assert!(test!(term::Atom::Latin1("ア".to_owned())).is_err());
+        assert!(test!(term::Atom::Latin1("é".repeat(MAXATOMLEN))).is_err());
+        assert!(test!(term::Atom::Latin1("é".repeat(MAXATOMLEN - 1))).is_ok());
+    }
+
     #[test]
     fn serialize_pid() {
         for (input, expected) in vec![
@@ -1,15 +1,72 @@
+use std::hash::{Hash, Hasher};
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, PartialEq, Debug)]
+use crate::consts::*;
+use crate::error::Error;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub enum Atom {
     Latin1(String),
     UTF8Small(String),
     UTF8(String),
 }
 
+impl Atom {
+    // at most 255 characters, SMALL_ATOM_UTF8_EXT when the name fits
+    pub fn new(s: &str) -> Result<Self, Error> {
+        match s.chars().count() {
+            u if u < MAXATOMLEN => Ok(Atom::from(s)),
+            u => Err(invalid_input!("new: {}", u)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Atom::Latin1(s) | Atom::UTF8Small(s) | Atom::UTF8(s) => s,
+        }
+    }
+}
+
 impl<'a> From<&'a str> for Atom {
     fn from(s: &'a str) -> Self {
-        Atom::UTF8Small(s.to_string())
+        if s.len() <= u8::MAX as usize {
+            Atom::UTF8Small(s.to_string())
+        } else {
+            Atom::UTF8(s.to_string())
+        }
+    }
+}
+
+// serializes a name as an atom without allocating an Atom
+pub struct AtomStr<'a>(pub &'a str);
+
+impl Serialize for AtomStr<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.0.len() {
+            u if u <= u8::MAX as usize => {
+                serializer.serialize_newtype_variant("Atom", 1, "UTF8Small", self.0)
+            }
+            _ => serializer.serialize_newtype_variant("Atom", 2, "UTF8", self.0),
+        }
+    }
+}
+
+// the same atom whichever encoding it arrived in
+impl PartialEq for Atom {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Atom {}
+
+impl Hash for Atom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
     }
 }
 
@@ -44,6 +101,7 @@ pub struct Trace(
     pub i64, // 4: prev
 );
 
+#[allow(dead_code)]
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub enum Msg {
     Send {
@@ -78,3 +136,40 @@ pub enum Msg {
         reason: String,
     },
 }
+
+#[cfg(test)]
+#[rustfmt::skip]
+mod tests {
+
+    use std::collections::HashSet;
+
+    use crate::to_vec;
+
+    use super::Atom;
+
+    #[test]
+    fn new() {
+        assert_eq!(Atom::UTF8Small("ok".to_owned()), Atom::new("ok").unwrap());
+        assert!(matches!(Atom::new(&"a".repeat(255)), Ok(Atom::UTF8Small(_))));
+        assert!(matches!(Atom::new(&"ア".repeat(255)), Ok(Atom::UTF8(_))));
+        assert!(Atom::new(&"a".repeat(256)).is_err());
+        assert!(Atom::new(&"ア".repeat(256)).is_err());
+
+        let v = to_vec(&Atom::new(&"ア".repeat(255)).unwrap()).unwrap();
+        assert_eq!(vec![0x76, 0x02, 0xfd], v[..3].to_vec());
+        assert!(to_vec(&Atom::UTF8("ア".repeat(256))).is_err());
+        assert!(to_vec(&Atom::UTF8Small("ア".repeat(86))).is_err());
+    }
+
+    #[test]
+    fn eq() {
+        assert_eq!(Atom::Latin1("ok".to_owned()), Atom::UTF8Small("ok".to_owned()));
+        assert_eq!(Atom::Latin1("café".to_owned()), Atom::UTF8("café".to_owned()));
+        assert_ne!(Atom::Latin1("ok".to_owned()), Atom::Latin1("error".to_owned()));
+
+        let set = vec![Atom::Latin1("ok".to_owned()), Atom::UTF8("ok".to_owned())]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(1, set.len());
+    }
+}
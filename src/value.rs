@@ -0,0 +1,184 @@
+use crate::consts::*;
+use crate::error::Error;
+
+#[derive(Debug)]
+pub enum Value<'a> {
+    Integer(bool, Vec<u8>), // sign, little-endian magnitude
+    Float(f64),
+    Atom(String),
+    Ref(String, u32, Vec<u32>),
+    Port(String, u64, u32),
+    Pid(String, u32, u32, u32),
+    Tuple(Vec<Value<'a>>),
+    Map(Vec<(Value<'a>, Value<'a>)>),
+    Nil,
+    List(Vec<Value<'a>>, Box<Value<'a>>),
+    String(&'a [u8]), // STRING_EXT, a proper list of small integers
+    Binary(&'a [u8]),
+}
+
+pub fn parse(buf: &[u8]) -> Result<(Value<'_>, usize), Error> {
+    let mut c = Cursor { buf, pos: 0 };
+    c.value().map(|v| (v, c.pos))
+}
+
+fn integer<'a>(neg: bool, mut vec: Vec<u8>) -> Value<'a> {
+    while vec.last() == Some(&0) {
+        vec.pop();
+    }
+    Value::Integer(neg && !vec.is_empty(), vec)
+}
+
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        match self.buf.get(self.pos..self.pos + len) {
+            Some(v) => {
+                self.pos += len;
+                Ok(v)
+            }
+            None => Err(invalid_data!("take: {}", len)),
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        self.take(1).map(|v| v[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        self.take(2).map(|v| u16::from_be_bytes([v[0], v[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        self.take(4)
+            .map(|v| u32::from_be_bytes([v[0], v[1], v[2], v[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(b))
+    }
+
+    fn latin1(&mut self, len: usize) -> Result<String, Error> {
+        self.take(len)
+            .map(|v| v.iter().map(|&u| u as char).collect())
+    }
+
+    fn utf8(&mut self, len: usize) -> Result<String, Error> {
+        let v = self.take(len)?;
+        std::str::from_utf8(v)
+            .map(|s| s.to_owned())
+            .map_err(Error::Str)
+    }
+
+    fn integer(&mut self, neg: bool, len: usize) -> Result<Value<'a>, Error> {
+        self.take(len).map(|v| integer(neg, v.to_vec()))
+    }
+
+    fn atom(&mut self) -> Result<String, Error> {
+        self.u8().and_then(|u| self.atom_ext(u))
+    }
+
+    fn atom_ext(&mut self, tag: u8) -> Result<String, Error> {
+        match tag {
+            ERL_ATOM_EXT => self.u16().and_then(|u| self.latin1(u as usize)),
+            ERL_SMALL_ATOM_EXT => self.u8().and_then(|u| self.latin1(u as usize)),
+            ERL_ATOM_UTF8_EXT => self.u16().and_then(|u| self.utf8(u as usize)),
+            ERL_SMALL_ATOM_UTF8_EXT => self.u8().and_then(|u| self.utf8(u as usize)),
+            u => Err(invalid_data!("atom: {}", u)),
+        }
+    }
+
+    fn values(&mut self, len: usize) -> Result<Vec<Value<'a>>, Error> {
+        (0..len).map(|_| self.value()).collect()
+    }
+
+    #[rustfmt::skip]
+    fn value(&mut self) -> Result<Value<'a>, Error> {
+        match self.u8()? {
+            ERL_SMALL_INTEGER_EXT => {
+                self.u8().map(|u| integer(false, vec![u]))
+            }
+            ERL_INTEGER_EXT => {
+                let i = self.u32()? as i32;
+                Ok(integer(i < 0, i.unsigned_abs().to_le_bytes().to_vec()))
+            }
+            ERL_SMALL_BIG_EXT => {
+                let n = self.u8()? as usize;
+                let s = self.u8()?;
+                self.integer(s != 0, n)
+            }
+            ERL_LARGE_BIG_EXT => {
+                let n = self.u32()? as usize;
+                let s = self.u8()?;
+                self.integer(s != 0, n)
+            }
+            NEW_FLOAT_EXT => {
+                self.u64().map(|u| Value::Float(f64::from_bits(u)))
+            }
+            u @ ERL_ATOM_EXT | u @ ERL_SMALL_ATOM_EXT | u @ ERL_ATOM_UTF8_EXT | u @ ERL_SMALL_ATOM_UTF8_EXT => {
+                self.atom_ext(u).map(Value::Atom)
+            }
+            ERL_NEWER_REFERENCE_EXT => {
+                let n = self.u16()? as usize;
+                let node = self.atom()?;
+                let creation = self.u32()?;
+                let ids = (0..n).map(|_| self.u32()).collect::<Result<_, _>>()?;
+                Ok(Value::Ref(node, creation, ids))
+            }
+            ERL_NEW_PORT_EXT => {
+                let node = self.atom()?;
+                let id = self.u32()? as u64;
+                self.u32().map(|creation| Value::Port(node, id, creation))
+            }
+            ERL_V4_PORT_EXT => {
+                let node = self.atom()?;
+                let id = self.u64()?;
+                self.u32().map(|creation| Value::Port(node, id, creation))
+            }
+            ERL_NEW_PID_EXT => {
+                let node = self.atom()?;
+                let num = self.u32()?;
+                let serial = self.u32()?;
+                self.u32().map(|creation| Value::Pid(node, num, serial, creation))
+            }
+            ERL_SMALL_TUPLE_EXT => {
+                let n = self.u8()? as usize;
+                self.values(n).map(Value::Tuple)
+            }
+            ERL_LARGE_TUPLE_EXT => {
+                let n = self.u32()? as usize;
+                self.values(n).map(Value::Tuple)
+            }
+            ERL_MAP_EXT => {
+                let n = self.u32()? as usize;
+                (0..n)
+                    .map(|_| Ok((self.value()?, self.value()?)))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Map)
+            }
+            ERL_NIL_EXT => {
+                Ok(Value::Nil)
+            }
+            ERL_STRING_EXT => {
+                let n = self.u16()? as usize;
+                self.take(n).map(Value::String)
+            }
+            ERL_LIST_EXT => {
+                let n = self.u32()? as usize;
+                let vec = self.values(n)?;
+                self.value().map(|t| Value::List(vec, Box::new(t)))
+            }
+            ERL_BINARY_EXT => {
+                let n = self.u32()? as usize;
+                self.take(n).map(Value::Binary)
+            }
+            u => Err(invalid_data!("value: {}", u)),
+        }
+    }
+}